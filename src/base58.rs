@@ -0,0 +1,91 @@
+use crate::hashes::hash256;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub fn encode(bytes: &[u8]) -> String {
+    // Each leading zero byte is encoded as a '1'
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    // Little endian base 58 digits of the number the bytes represent
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in &bytes[zeros..] {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let prefix = std::iter::repeat_n('1', zeros);
+    let digits = digits
+        .iter()
+        .rev()
+        .map(|digit| char::from(ALPHABET[usize::from(*digit)]));
+
+    prefix.chain(digits).collect()
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>, crate::Error> {
+    let zeros = s.chars().take_while(|c| *c == '1').count();
+
+    // Little endian base 256 digits of the number the string represents
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.chars().skip(zeros) {
+        let value = ALPHABET
+            .iter()
+            .position(|symbol| char::from(*symbol) == c)
+            .ok_or_else(|| crate::Error::InvalidBase58(format!("Invalid character {c:?}")))?;
+
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut result = vec![0u8; zeros];
+    result.extend(bytes.iter().rev());
+
+    Ok(result)
+}
+
+/// Base58 encoding of the bytes followed by the first four bytes of their hash256.
+pub fn encode_check(bytes: &[u8]) -> String {
+    let checksum = hash256(bytes);
+
+    encode(&[bytes, &checksum[..4]].concat())
+}
+
+/// Decodes a Base58Check string, verifies its checksum and returns the payload without it.
+pub fn decode_check(s: &str) -> Result<Vec<u8>, crate::Error> {
+    let mut bytes = decode(s)?;
+
+    if bytes.len() < 4 {
+        return Err(crate::Error::InvalidBase58(format!(
+            "{s} is too short to hold a checksum"
+        )));
+    }
+
+    let checksum = bytes.split_off(bytes.len() - 4);
+    let expected = hash256(&bytes);
+
+    if checksum != expected[..4] {
+        return Err(crate::Error::ChecksumMismatch(format!(
+            "Expected {}, got {}",
+            crate::hex::encode(&expected[..4]),
+            crate::hex::encode(&checksum)
+        )));
+    }
+
+    Ok(bytes)
+}
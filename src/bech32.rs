@@ -0,0 +1,249 @@
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// Longest string BIP-173 allows.
+const MAX_LEN: usize = 90;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+    /// BIP-173, used by segwit v0 addresses.
+    Bech32,
+    /// BIP-350, used by segwit v1 and later addresses.
+    Bech32m,
+}
+
+impl Variant {
+    /// What the checksum of a valid string makes the polymod of.
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk = 1u32;
+
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ u32::from(*value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+
+    chk
+}
+
+/// The human-readable part as fed into the checksum: high bits, a zero, then low bits.
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut result: Vec<u8> = hrp.bytes().map(|byte| byte >> 5).collect();
+    result.push(0);
+    result.extend(hrp.bytes().map(|byte| byte & 0x1f));
+
+    result
+}
+
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> [u8; 6] {
+    let values = [&hrp_expand(hrp)[..], data, &[0; 6]].concat();
+    let polymod = polymod(&values) ^ variant.constant();
+
+    let mut checksum = [0u8; 6];
+    for (i, value) in checksum.iter_mut().enumerate() {
+        *value = ((polymod >> (5 * (5 - i))) & 0x1f) as u8;
+    }
+
+    checksum
+}
+
+/// Encodes 5-bit `data` under the lowercase human-readable part `hrp`.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> Result<String, crate::Error> {
+    let invalid = |reason: &str| Err(crate::Error::InvalidBech32(reason.to_string()));
+
+    if hrp.is_empty() || hrp.len() > 83 {
+        return invalid("human-readable part must be 1 to 83 characters");
+    }
+    if hrp
+        .bytes()
+        .any(|byte| !(33..=126).contains(&byte) || byte.is_ascii_uppercase())
+    {
+        return invalid("human-readable part must be lowercase printable ASCII");
+    }
+    if data.iter().any(|value| *value > 31) {
+        return invalid("data values must fit in 5 bits");
+    }
+    if hrp.len() + 1 + data.len() + 6 > MAX_LEN {
+        return invalid("too long");
+    }
+
+    let checksum = create_checksum(hrp, data, variant);
+
+    let mut result = format!("{hrp}1");
+    result.extend(
+        data.iter()
+            .chain(&checksum)
+            .map(|value| char::from(CHARSET[usize::from(*value)])),
+    );
+
+    Ok(result)
+}
+
+/// Decodes a bech32 or bech32m string into its lowercase human-readable part and 5-bit data,
+/// without the checksum.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>, Variant), crate::Error> {
+    let invalid = |reason: &str| Err(crate::Error::InvalidBech32(reason.to_string()));
+
+    if s.len() > MAX_LEN {
+        return invalid("too long");
+    }
+    if s.bytes().any(|byte| !(33..=126).contains(&byte)) {
+        return invalid("characters must be printable ASCII");
+    }
+    if s.bytes().any(|byte| byte.is_ascii_lowercase())
+        && s.bytes().any(|byte| byte.is_ascii_uppercase())
+    {
+        return invalid("mixed case");
+    }
+
+    let s = s.to_ascii_lowercase();
+
+    let Some(separator) = s.rfind('1') else {
+        return invalid("missing separator");
+    };
+    let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+
+    if hrp.is_empty() {
+        return invalid("empty human-readable part");
+    }
+    if data.len() < 6 {
+        return invalid("checksum too short");
+    }
+
+    let data = data
+        .bytes()
+        .map(|byte| {
+            CHARSET
+                .iter()
+                .position(|c| *c == byte)
+                .map(|value| value as u8)
+        })
+        .collect::<Option<Vec<u8>>>();
+    let Some(mut data) = data else {
+        return invalid("character outside the charset");
+    };
+
+    let polymod = polymod(&[&hrp_expand(hrp)[..], &data].concat());
+    let variant = [Variant::Bech32, Variant::Bech32m]
+        .into_iter()
+        .find(|variant| variant.constant() == polymod)
+        .ok_or_else(|| crate::Error::ChecksumMismatch(format!("Invalid checksum in {s}")))?;
+
+    data.truncate(data.len() - 6);
+
+    Ok((hrp.to_string(), data, variant))
+}
+
+/// Regroups `data` from `from` bits per value to `to` bits per value. Without `pad`, leftover
+/// bits must be fewer than `from` and all zero.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, crate::Error> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut result = Vec::new();
+    let max = (1u32 << to) - 1;
+
+    for value in data {
+        let value = u32::from(*value);
+        if value >> from != 0 {
+            return Err(crate::Error::InvalidBech32(format!(
+                "{value} does not fit in {from} bits"
+            )));
+        }
+
+        acc = (acc << from) | value;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(crate::Error::InvalidBech32("invalid padding".to_string()));
+    }
+
+    Ok(result)
+}
+
+/// Encodes a segwit address, using bech32 for version 0 and bech32m for later versions.
+pub fn encode_segwit(hrp: &str, version: u8, program: &[u8]) -> Result<String, crate::Error> {
+    check_witness_program(version, program)?;
+
+    let variant = if version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+
+    let mut data = vec![version];
+    data.extend(convert_bits(program, 8, 5, true)?);
+
+    encode(hrp, &data, variant)
+}
+
+/// Decodes a segwit address, which must be for `hrp`, into its witness version and program.
+pub fn decode_segwit(hrp: &str, address: &str) -> Result<(u8, Vec<u8>), crate::Error> {
+    let (decoded_hrp, data, variant) = decode(address)?;
+
+    if decoded_hrp != hrp {
+        return Err(crate::Error::InvalidBech32(format!(
+            "Expected human-readable part {hrp}, found {decoded_hrp}"
+        )));
+    }
+
+    let Some((version, program)) = data.split_first() else {
+        return Err(crate::Error::InvalidBech32(
+            "missing witness version".to_string(),
+        ));
+    };
+    let program = convert_bits(program, 5, 8, false)?;
+
+    check_witness_program(*version, &program)?;
+
+    let expected_variant = if *version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    if variant != expected_variant {
+        return Err(crate::Error::InvalidBech32(format!(
+            "Witness version {version} must use {expected_variant:?}"
+        )));
+    }
+
+    Ok((*version, program))
+}
+
+/// BIP-141 limits: versions up to 16, programs of 2 to 40 bytes, and 20 or 32 bytes for v0.
+pub(crate) fn check_witness_program(version: u8, program: &[u8]) -> Result<(), crate::Error> {
+    if version > 16 {
+        return Err(crate::Error::InvalidBech32(format!(
+            "Invalid witness version {version}"
+        )));
+    }
+    if !(2..=40).contains(&program.len()) || (version == 0 && ![20, 32].contains(&program.len())) {
+        return Err(crate::Error::InvalidBech32(format!(
+            "Invalid witness program length {} for version {version}",
+            program.len()
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use std::io::Read;
+
+use crypto_bigint::U256;
+
+use crate::{hashes::hash256, hex};
+
+/// Bits of the easiest target allowed on mainnet and testnet, the one of the genesis block.
+pub const MAX_TARGET_BITS: u32 = 0x1d00ffff;
+
+/// The sign bit of the mantissa of compact targets. Targets are never negative.
+const COMPACT_SIGN_BIT: u32 = 0x00800000;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockHeader {
+    pub version: u32,
+    /// Hash of the previous block header, in display order.
+    pub prev_block: [u8; 32],
+    /// Merkle root of the block transactions, in display order.
+    pub merkle_root: [u8; 32],
+    pub timestamp: u32,
+    /// The target the header hash must not exceed, in compact form.
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, crate::Error> {
+        let version = read_u32(reader)?;
+        let prev_block = read_hash(reader)?;
+        let merkle_root = read_hash(reader)?;
+        let timestamp = read_u32(reader)?;
+        let bits = read_u32(reader)?;
+        let nonce = read_u32(reader)?;
+
+        Ok(BlockHeader {
+            version,
+            prev_block,
+            merkle_root,
+            timestamp,
+            bits,
+            nonce,
+        })
+    }
+
+    pub fn serialize(&self) -> [u8; 80] {
+        let mut result = [0u8; 80];
+        result[..4].copy_from_slice(&self.version.to_le_bytes());
+        result[4..36].copy_from_slice(&self.prev_block);
+        result[4..36].reverse();
+        result[36..68].copy_from_slice(&self.merkle_root);
+        result[36..68].reverse();
+        result[68..72].copy_from_slice(&self.timestamp.to_le_bytes());
+        result[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        result[76..].copy_from_slice(&self.nonce.to_le_bytes());
+
+        result
+    }
+
+    /// hash256 of the serialization, in display order.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hash = hash256(&self.serialize());
+        hash.reverse();
+
+        hash
+    }
+
+    /// Hex of the block hash, as shown by block explorers.
+    pub fn id(&self) -> String {
+        hex::encode(&self.hash())
+    }
+
+    pub fn target(&self) -> Result<U256, crate::Error> {
+        bits_to_target(self.bits)
+    }
+
+    /// How many times harder the target is than the easiest one, as reported by `getdifficulty`.
+    pub fn difficulty(&self) -> f64 {
+        difficulty(self.bits)
+    }
+
+    /// Whether the target is valid and not easier than the maximum, and the hash is not above it.
+    pub fn check_pow(&self) -> bool {
+        let target = match self.target() {
+            Ok(target) => target,
+            Err(_) => return false,
+        };
+        let max_target = bits_to_target(MAX_TARGET_BITS).expect("valid bits");
+        if target == U256::ZERO || target > max_target {
+            return false;
+        }
+
+        // The hash read as a little endian number
+        U256::from_le_slice(&hash256(&self.serialize())) <= target
+    }
+}
+
+/// Expands compact bits: the high byte is the length of the target in bytes, the low three its
+/// leading bytes.
+pub fn bits_to_target(bits: u32) -> Result<U256, crate::Error> {
+    let exponent = bits >> 24;
+    let mantissa = bits & (COMPACT_SIGN_BIT - 1);
+
+    // Zero whatever the exponent, which could otherwise shift past 256 bits
+    if mantissa == 0 {
+        return Ok(U256::ZERO);
+    }
+
+    if bits & COMPACT_SIGN_BIT != 0 {
+        return Err(crate::Error::ValueError(format!(
+            "Negative target in bits {bits:#010x}"
+        )));
+    }
+    if exponent > 34 || (mantissa > 0xff && exponent > 33) || (mantissa > 0xffff && exponent > 32) {
+        return Err(crate::Error::ValueError(format!(
+            "Target in bits {bits:#010x} does not fit 256 bits"
+        )));
+    }
+
+    let mantissa = U256::from_u32(mantissa);
+    if exponent <= 3 {
+        Ok(mantissa.shr_vartime(8 * (3 - exponent)))
+    } else {
+        Ok(mantissa.shl_vartime(8 * (exponent - 3)))
+    }
+}
+
+/// Compacts a target into bits, dropping all but its three leading bytes.
+pub fn target_to_bits(target: U256) -> u32 {
+    let mut size = target.bits().div_ceil(8);
+    let mut mantissa = if size <= 3 {
+        low_u32(&target) << (8 * (3 - size))
+    } else {
+        low_u32(&target.shr_vartime(8 * (size - 3)))
+    };
+
+    // A leading byte above 0x7f would read as a negative mantissa
+    if mantissa & COMPACT_SIGN_BIT != 0 {
+        mantissa >>= 8;
+        size += 1;
+    }
+
+    (size << 24) | mantissa
+}
+
+/// The maximum target divided by the target of `bits`.
+pub fn difficulty(bits: u32) -> f64 {
+    let mut shift = (bits >> 24) & 0xff;
+    let mut difficulty = f64::from(0x0000ffff) / f64::from(bits & 0x00ffffff);
+
+    while shift < 29 {
+        difficulty *= 256.0;
+        shift += 1;
+    }
+    while shift > 29 {
+        difficulty /= 256.0;
+        shift -= 1;
+    }
+
+    difficulty
+}
+
+fn low_u32(num: &U256) -> u32 {
+    let bytes = num.to_be_bytes();
+
+    u32::from_be_bytes(bytes[28..].try_into().expect("4 bytes"))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, crate::Error> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_hash<R: Read>(reader: &mut R) -> Result<[u8; 32], crate::Error> {
+    let mut hash = [0u8; 32];
+    reader.read_exact(&mut hash)?;
+    hash.reverse();
+
+    Ok(hash)
+}
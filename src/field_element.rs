@@ -34,6 +34,76 @@ impl<const P: u128> FieldElement<P> {
 
         Some(FieldElement(num))
     }
+
+    fn pow_u128(self, exponent: u128) -> FieldElement<P> {
+        let mut result = FieldElement(1 % P);
+        let mut current = self;
+        let mut exponent = exponent;
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * current;
+            }
+            current = current * current;
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    /// Legendre symbol of the element: 1 if it is a non-zero square, -1 if it is not a square
+    /// and 0 if it is zero.
+    pub fn legendre_symbol(self) -> i8 {
+        match *self.pow_u128((P - 1) / 2) {
+            0 => 0,
+            1 => 1,
+            _ => -1,
+        }
+    }
+
+    /// One of the square roots of the element, or `None` if it is not a square.
+    pub fn sqrt(self) -> Option<FieldElement<P>> {
+        match self.legendre_symbol() {
+            0 => return Some(self),
+            -1 => return None,
+            _ => {}
+        }
+
+        if P % 4 == 3 {
+            return Some(self.pow_u128((P + 1) / 4));
+        }
+
+        // Tonelli-Shanks: write P - 1 as q * 2^s with q odd
+        let s = (P - 1).trailing_zeros();
+        let q = (P - 1) >> s;
+
+        let mut z = FieldElement(2);
+        while z.legendre_symbol() != -1 {
+            z = z + FieldElement(1);
+        }
+
+        let mut m = s;
+        let mut c = z.pow_u128(q);
+        let mut t = self.pow_u128(q);
+        let mut r = self.pow_u128(q.div_ceil(2));
+
+        while *t != 1 {
+            let mut i = 0;
+            let mut t_squared = t;
+            while *t_squared != 1 {
+                t_squared = t_squared * t_squared;
+                i += 1;
+            }
+
+            let b = c.pow_u128(1 << (m - i - 1));
+            m = i;
+            c = b * b;
+            t = t * c;
+            r = r * b;
+        }
+
+        Some(r)
+    }
 }
 
 impl<const P: u128> std::ops::Deref for FieldElement<P> {
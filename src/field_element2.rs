@@ -1,11 +1,11 @@
 #[allow(unused)]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct FieldElement {
     num: crypto_bigint::U256,
     prime: crypto_bigint::U256,
 }
 
-#[allow(unused)]
+#[allow(unused, clippy::should_implement_trait)]
 impl FieldElement {
     pub fn new(num: crypto_bigint::U256, prime: crypto_bigint::U256) -> Result<Self, crate::Error> {
         if num >= prime {
@@ -17,6 +17,23 @@ impl FieldElement {
         Ok(FieldElement { num, prime })
     }
 
+    /// Same as `new`, but usable in const contexts. Panics if `num` is not in the field range.
+    pub const fn new_const(num: crypto_bigint::U256, prime: crypto_bigint::U256) -> Self {
+        if !matches!(num.cmp_vartime(&prime), std::cmp::Ordering::Less) {
+            panic!("field element should be less than prime")
+        }
+
+        FieldElement { num, prime }
+    }
+
+    pub fn num(&self) -> crypto_bigint::U256 {
+        self.num
+    }
+
+    pub fn prime(&self) -> crypto_bigint::U256 {
+        self.prime
+    }
+
     pub fn add(self, rhs: FieldElement) -> Result<FieldElement, crate::Error> {
         if self.prime != rhs.prime {
             return Err(crate::Error::ValueError(
@@ -42,6 +59,160 @@ impl FieldElement {
             prime: self.prime,
         })
     }
+
+    pub fn mul(self, rhs: FieldElement) -> Result<FieldElement, crate::Error> {
+        if self.prime != rhs.prime {
+            return Err(crate::Error::ValueError(
+                "Primes must be the same".to_string(),
+            ));
+        }
+
+        let prime = crypto_bigint::NonZero::new(self.prime).unwrap();
+
+        Ok(FieldElement {
+            num: self.num.mul_mod_vartime(&rhs.num, &prime),
+            prime: self.prime,
+        })
+    }
+
+    /// Multiplies by the inverse of `rhs`, so `rhs` must not be zero.
+    pub fn div(self, rhs: FieldElement) -> Result<FieldElement, crate::Error> {
+        if self.prime != rhs.prime {
+            return Err(crate::Error::ValueError(
+                "Primes must be the same".to_string(),
+            ));
+        }
+
+        self.mul(rhs.inverse()?)
+    }
+
+    pub fn inverse(self) -> Result<FieldElement, crate::Error> {
+        if self.num == crypto_bigint::U256::ZERO {
+            return Err(crate::Error::ValueError(
+                "Zero has no multiplicative inverse".to_string(),
+            ));
+        }
+
+        let num: Option<crypto_bigint::U256> = self.num.inv_mod(&self.prime).into();
+        let num = num.ok_or_else(|| {
+            crate::Error::ValueError(format!("{} has no inverse mod {}", self.num, self.prime))
+        })?;
+
+        Ok(FieldElement {
+            num,
+            prime: self.prime,
+        })
+    }
+
+    /// Square-and-multiply exponentiation.
+    pub fn pow_u256(self, exponent: crypto_bigint::U256) -> FieldElement {
+        let mut result = FieldElement {
+            num: crypto_bigint::U256::ONE,
+            prime: self.prime,
+        };
+        let mut current = self;
+
+        for i in 0..exponent.bits_vartime() {
+            if exponent.bit_vartime(i) {
+                result = result.mul(current).expect("same prime");
+            }
+            current = current.mul(current).expect("same prime");
+        }
+
+        result
+    }
+
+    /// Legendre symbol of the element: 1 if it is a non-zero square, -1 if it is not a square
+    /// and 0 if it is zero.
+    pub fn legendre_symbol(self) -> i8 {
+        let exponent = (self.prime - crypto_bigint::U256::ONE).shr_vartime(1);
+        let result = self.pow_u256(exponent).num;
+
+        if result == crypto_bigint::U256::ZERO {
+            0
+        } else if result == crypto_bigint::U256::ONE {
+            1
+        } else {
+            -1
+        }
+    }
+
+    /// One of the square roots of the element, or `None` if it is not a square.
+    pub fn sqrt(self) -> Option<FieldElement> {
+        match self.legendre_symbol() {
+            0 => return Some(self),
+            -1 => return None,
+            _ => {}
+        }
+
+        let one = FieldElement {
+            num: crypto_bigint::U256::ONE,
+            prime: self.prime,
+        };
+
+        // Modulo 2 every element is its own square root, and there is no non-residue for
+        // Tonelli-Shanks to find
+        if self.prime == crypto_bigint::U256::from_u8(2) {
+            return Some(self);
+        }
+
+        if self.prime.as_words()[0] & 3 == 3 {
+            let exponent = (self.prime + crypto_bigint::U256::ONE).shr_vartime(2);
+            return Some(self.pow_u256(exponent));
+        }
+
+        // Tonelli-Shanks: write prime - 1 as q * 2^s with q odd
+        let prime_minus_one = self.prime - crypto_bigint::U256::ONE;
+        let s = prime_minus_one.trailing_zeros_vartime();
+        let q = prime_minus_one.shr_vartime(s);
+
+        let mut z = one.add(one).expect("same prime");
+        while z.legendre_symbol() != -1 {
+            z = z.add(one).expect("same prime");
+        }
+
+        let mut m = s;
+        let mut c = z.pow_u256(q);
+        let mut t = self.pow_u256(q);
+        let mut r = self.pow_u256((q + crypto_bigint::U256::ONE).shr_vartime(1));
+
+        while t != one {
+            let mut i = 0;
+            let mut t_squared = t;
+            while t_squared != one {
+                t_squared = t_squared.mul(t_squared).expect("same prime");
+                i += 1;
+            }
+
+            let b = c.pow_u256(crypto_bigint::U256::ONE.shl_vartime(m - i - 1));
+            m = i;
+            c = b.mul(b).expect("same prime");
+            t = t.mul(c).expect("same prime");
+            r = r.mul(b).expect("same prime");
+        }
+
+        Some(r)
+    }
+}
+
+impl crate::Pow for FieldElement {
+    type Output = FieldElement;
+
+    fn pow(self, exponent: i32) -> FieldElement {
+        let prime_minus_one = crypto_bigint::NonZero::new(self.prime - crypto_bigint::U256::ONE)
+            .expect("prime should be greater than one");
+
+        let magnitude = crypto_bigint::U256::from_u32(exponent.unsigned_abs());
+        let magnitude = magnitude.rem_vartime(&prime_minus_one);
+
+        let exponent = if exponent < 0 {
+            crypto_bigint::U256::ZERO.sub_mod(&magnitude, &prime_minus_one)
+        } else {
+            magnitude
+        };
+
+        self.pow_u256(exponent)
+    }
 }
 
 impl std::fmt::Display for FieldElement {
@@ -0,0 +1,27 @@
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn decode(hex: &str) -> Result<Vec<u8>, crate::Error> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(crate::Error::ValueError(
+            "Hex string must have an even length".to_string(),
+        ));
+    }
+
+    // from_str_radix alone would accept a sign, as in "+f"
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(crate::Error::ValueError(format!(
+            "Invalid hex string {hex}"
+        )));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| crate::Error::ValueError(format!("Invalid hex string {hex}")))
+        })
+        .collect()
+}
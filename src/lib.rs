@@ -1,6 +1,21 @@
+pub mod base58;
+pub mod bech32;
+pub mod block;
 mod field_element;
-mod field_element2;
-mod point;
+pub mod field_element2;
+pub mod hashes;
+pub mod hex;
+pub mod point;
+pub mod private_key;
+pub mod rfc6979;
+pub mod schnorr;
+pub mod script;
+pub mod secp256k1;
+pub mod signature;
+pub mod taproot;
+pub mod tx;
+pub mod tx_builder;
+pub mod varint;
 
 // TODO: implement ops vs checked_ops (i.e. checked_add vs add)
 
@@ -10,16 +25,95 @@ pub trait Pow {
     fn pow(self, rhs: i32) -> Self::Output;
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// Version byte of P2PKH addresses.
+    pub fn p2pkh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet => 0x6f,
+        }
+    }
+
+    /// Version byte of P2SH addresses.
+    pub fn p2sh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet => 0xc4,
+        }
+    }
+
+    /// Human-readable part of segwit addresses.
+    pub fn bech32_hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet => "tb",
+        }
+    }
+
+    /// Version byte of WIF private keys.
+    pub fn wif_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x80,
+            Network::Testnet => 0xef,
+        }
+    }
+}
+
 #[allow(unused)]
 #[derive(Debug)]
 pub enum Error {
     ValueError(String),
     TypeError(String),
     Conversion(std::num::TryFromIntError),
+    /// The bytes are not a DER encoded signature.
+    InvalidDer(String),
+    /// The bytes are a DER encoded signature, but not in the strict form required by BIP-66.
+    NonCanonicalDer(String),
+    InvalidBase58(String),
+    InvalidBech32(String),
+    ChecksumMismatch(String),
+    Io(std::io::Error),
+    /// The previous output an input spends could not be found.
+    MissingUtxo(String),
+    /// Script evaluation failed, at `opcode` if the failure is tied to one.
+    Script {
+        opcode: Option<script::Opcode>,
+        error: script::ScriptError,
+    },
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    /// Helpers shared by the test modules below.
+    mod util {
+        use crate::{hex, script::Script, tx::Tx};
+
+        /// A `U256` from big endian hex, which may be shorter than 64 digits.
+        pub(super) fn u256(hex: &str) -> crypto_bigint::U256 {
+            crypto_bigint::U256::from_be_hex(&format!("{hex:0>64}"))
+        }
+
+        pub(super) fn parse_tx(tx_hex: &str) -> Tx {
+            Tx::parse(&mut hex::decode(tx_hex).unwrap().as_slice()).unwrap()
+        }
+
+        pub(super) fn script(script_hex: &str) -> Script {
+            Script::parse_raw(&hex::decode(script_hex).unwrap()).unwrap()
+        }
+    }
+
     mod chapter_1 {
         use crate::{field_element::FieldElement, Pow};
 
@@ -247,6 +341,185 @@ mod tests {
 
             assert_eq!(actual, expected);
         }
+
+        #[test]
+        fn test_pow_again() {
+            let prime = crypto_bigint::U256::from_u128(19);
+
+            let a =
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::from_u128(7), prime)
+                    .unwrap();
+            let expected =
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::from_u128(1), prime)
+                    .unwrap();
+            let actual = a.pow(3);
+
+            assert_eq!(actual, expected);
+
+            let a =
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::from_u128(9), prime)
+                    .unwrap();
+            let expected =
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::from_u128(7), prime)
+                    .unwrap();
+            let actual = a.pow_u256(crypto_bigint::U256::from_u128(12));
+
+            assert_eq!(actual, expected);
+        }
+
+        #[test]
+        fn exercise4_again() {
+            // 95*45*31
+            let prime = crypto_bigint::U256::from_u128(97);
+            let element = |num| {
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::from_u128(num), prime)
+                    .unwrap()
+            };
+
+            let expected = element(23);
+            let actual = element(95)
+                .mul(element(45))
+                .unwrap()
+                .mul(element(31))
+                .unwrap();
+
+            assert_eq!(actual, expected);
+
+            // 17*13*19*44
+            let expected = element(68);
+            let actual = element(17)
+                .mul(element(13))
+                .unwrap()
+                .mul(element(19))
+                .unwrap()
+                .mul(element(44))
+                .unwrap();
+
+            assert_eq!(actual, expected);
+
+            // 12^7 * 77^49
+            let expected = element(63);
+            let actual = element(12).pow(7).mul(element(77).pow(49)).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+
+        #[test]
+        fn exercise8_again() {
+            let prime = crypto_bigint::U256::from_u128(31);
+            let element = |num| {
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::from_u128(num), prime)
+                    .unwrap()
+            };
+
+            // 3/24
+            let expected = element(4);
+            let actual = element(3).div(element(24)).unwrap();
+
+            assert_eq!(actual, expected);
+
+            // 17^-3
+            let expected = element(29);
+            let actual = element(17).pow(-3);
+
+            assert_eq!(actual, expected);
+
+            // 4^-4 * 11
+            let expected = element(13);
+            let actual = element(4).pow(-4).mul(element(11)).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+
+        #[test]
+        fn inverse_of_zero() {
+            let prime = crypto_bigint::U256::from_u128(31);
+            let zero =
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::ZERO, prime).unwrap();
+            let one =
+                crate::field_element2::FieldElement::new(crypto_bigint::U256::ONE, prime).unwrap();
+
+            assert!(zero.inverse().is_err());
+            assert!(one.div(zero).is_err());
+            assert_eq!(one.inverse().unwrap(), one);
+        }
+
+        fn check_sqrt<const P: u128>() {
+            let mut squares = 0;
+
+            for num in 0..P {
+                let a = FieldElement::<P>::new(num);
+                let is_square = (0..P).any(|root| FieldElement::<P>::new(root).pow(2) == a);
+
+                match a.sqrt() {
+                    Some(root) => {
+                        assert_eq!(root * root, a);
+                        squares += 1;
+                    }
+                    None => assert!(!is_square),
+                }
+
+                let expected = match (num, is_square) {
+                    (0, _) => 0,
+                    (_, true) => 1,
+                    (_, false) => -1,
+                };
+                assert_eq!(a.legendre_symbol(), expected);
+            }
+
+            assert_eq!(squares, (P - 1) / 2 + 1);
+        }
+
+        #[test]
+        fn sqrt() {
+            // 223 and 31 are 3 mod 4, 97 is 1 mod 4 and goes through Tonelli-Shanks
+            check_sqrt::<223>();
+            check_sqrt::<97>();
+            check_sqrt::<31>();
+        }
+
+        #[test]
+        fn sqrt_again() {
+            for prime in [223u128, 97, 31, 17, 2] {
+                let prime = crypto_bigint::U256::from_u128(prime);
+                let mut squares = 0;
+
+                for num in 0..prime.as_words()[0] {
+                    let a = crate::field_element2::FieldElement::new(
+                        crypto_bigint::U256::from_u64(num),
+                        prime,
+                    )
+                    .unwrap();
+
+                    if let Some(root) = a.sqrt() {
+                        assert_eq!(root.mul(root).unwrap(), a);
+                        assert_ne!(a.legendre_symbol(), -1);
+                        squares += 1;
+                    } else {
+                        assert_eq!(a.legendre_symbol(), -1);
+                    }
+                }
+
+                assert_eq!(squares, prime.as_words()[0] / 2 + 1);
+            }
+
+            // 2 is a square modulo the secp256k1 prime, 3 is not
+            let two = crate::field_element2::FieldElement::new(
+                crypto_bigint::U256::from_u8(2),
+                crate::secp256k1::P,
+            )
+            .unwrap();
+            let root = two.sqrt().unwrap();
+            assert_eq!(root.mul(root).unwrap(), two);
+
+            let three = crate::field_element2::FieldElement::new(
+                crypto_bigint::U256::from_u8(3),
+                crate::secp256k1::P,
+            )
+            .unwrap();
+            assert_eq!(three.legendre_symbol(), -1);
+            assert!(three.sqrt().is_none());
+        }
     }
 
     mod chapter2 {
@@ -289,6 +562,7 @@ mod tests {
     }
 
     mod chapter3 {
+        use super::util::u256;
         use crate::{
             field_element::FieldElement,
             point::{Curve, Point},
@@ -507,5 +781,2906 @@ mod tests {
 
             assert_eq!(actual, expected);
         }
+
+        #[test]
+        fn secp256k1_generator() {
+            use crate::secp256k1::{Secp256k1, G, N};
+
+            let Point::Real(x, y) = G else {
+                panic!("G should not be the point at infinity");
+            };
+            assert!(Point::<Secp256k1>::new(Some((x, y))).is_ok());
+
+            let actual = G.scalar_mul_u256(N);
+            let expected = Point::<Secp256k1>::Infinity;
+
+            assert_eq!(actual, expected);
+        }
+
+        #[test]
+        fn public_points() {
+            use crate::private_key::PrivateKey;
+
+            let cases = [
+                (
+                    u256("7"),
+                    "5cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc",
+                    "6aebca40ba255960a3178d6d861a54dba813d0b813fde7b5a5082628087264da",
+                ),
+                (
+                    u256("5cd"),
+                    "c982196a7466fbbbb0e27a940b6af926c1a74d5ad07128c82824a11b5398afda",
+                    "7a91f9eae64438afb9ce6448a1c133db2d8fb9254e4546b6f001637d50901f55",
+                ),
+                (
+                    u256("100000000000000000000000000000000"),
+                    "8f68b9d2f63b5f339239c1ad981f162ee88c5678723ea3351b7b444c9ec4c0da",
+                    "662a9f2dba063986de1d90c2b6be215dbbea2cfe95510bfdf23cbf79501fff82",
+                ),
+                (
+                    u256("1000000000000000000000000000000000000000000000000000080000000"),
+                    "9577ff57c8234558f293df502ca4f09cbc65a6572c842b39b366f21717945116",
+                    "10b49c67fa9365ad7b90dab070be339a1daf9052373ec30ffae4f72d5e66d053",
+                ),
+            ];
+
+            for (secret, x, y) in cases {
+                let actual = PrivateKey::new(secret).unwrap().point();
+                let expected = crate::secp256k1::PublicKey::new(Some((
+                    crate::secp256k1::S256Field::new(u256(x)),
+                    crate::secp256k1::S256Field::new(u256(y)),
+                )))
+                .unwrap();
+
+                assert_eq!(actual, expected);
+            }
+        }
+
+        #[test]
+        fn verify() {
+            use crate::{
+                secp256k1::{PublicKey, S256Field},
+                signature::Signature,
+            };
+
+            let point = PublicKey::new(Some((
+                S256Field::new(u256(
+                    "887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c",
+                )),
+                S256Field::new(u256(
+                    "61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34",
+                )),
+            )))
+            .unwrap();
+
+            let z = u256("ec208baa0fc1c19f708a9ca96fdeff3ac3f230bb4a7ba4aede4942ad003c0f60");
+            let sig = Signature::new(
+                u256("ac8d1c87e51d0d441be8b3dd5b05c8795b48875dffe00b7ffcfac23010d3a395"),
+                u256("68342ceff8935ededd102dd876ffd6ba72d6a427a3edb13d26eb0781cb423c4"),
+            );
+            assert!(point.verify(z, &sig));
+
+            let z = u256("7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d");
+            let sig = Signature::new(
+                u256("eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c"),
+                u256("c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab6"),
+            );
+            assert!(point.verify(z, &sig));
+
+            let point = PublicKey::new(Some((
+                S256Field::new(u256(
+                    "04519fac3d910ca7e7138f7013706f619fa8f033e6ec6e09370ea38cee6a7574",
+                )),
+                S256Field::new(u256(
+                    "82b51eab8c27c66e26c858a079bcdf4f1ada34cec420cafc7eac1a42216fb6c4",
+                )),
+            )))
+            .unwrap();
+
+            let z = u256("bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423");
+            let sig = Signature::new(
+                u256("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6"),
+                u256("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec"),
+            );
+            assert!(point.verify(z, &sig));
+            assert!(!point.verify(z + crypto_bigint::U256::ONE, &sig));
+        }
+
+        #[test]
+        fn sign() {
+            use crate::private_key::PrivateKey;
+
+            let private_key = PrivateKey::new(u256("3039")).unwrap();
+            let z = u256("969f6056aa26f7d2795fd013fe88868d09c9f6aed96965016e1936ae47060d48");
+            let k = u256("499602d2");
+
+            let sig = private_key.sign_with_k(z, k).unwrap();
+
+            assert!(private_key.point().verify(z, &sig));
+            assert!(!private_key
+                .point()
+                .verify(z + crypto_bigint::U256::ONE, &sig));
+            assert!(private_key
+                .sign_with_k(z, crypto_bigint::U256::ZERO)
+                .is_err());
+
+            let sig = private_key.sign(z).unwrap();
+
+            assert!(private_key.point().verify(z, &sig));
+        }
+
+        #[test]
+        fn rfc6979_nist_p256() {
+            use crate::{hashes::sha256, rfc6979::generate_k};
+
+            let order = u256("FFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551");
+            let secret = u256("C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721");
+
+            let z = crypto_bigint::U256::from_be_slice(&sha256(b"sample"));
+            let expected = u256("A6E3C57DD01ABE90086538398355DD4C3B17AA873382B0F24D6129493D8AAD60");
+            assert_eq!(generate_k(secret, z, order), expected);
+
+            let z = crypto_bigint::U256::from_be_slice(&sha256(b"test"));
+            let expected = u256("D16B6AE827F17175E040871A1C7EC3500192C4C92677336EC2537ACAEE0008E0");
+            assert_eq!(generate_k(secret, z, order), expected);
+        }
+
+        #[test]
+        fn deterministic_k() {
+            use crate::{hashes::sha256, private_key::PrivateKey, secp256k1::N};
+
+            let cases: [(crypto_bigint::U256, &[u8], &str); 4] = [
+                (
+                    u256("1"),
+                    b"Satoshi Nakamoto",
+                    "8F8A276C19F4149656B280621E358CCE24F5F52542772691EE69063B74F15D15",
+                ),
+                (
+                    u256("1"),
+                    b"All those moments will be lost in time, like tears in rain. Time to die...",
+                    "38AA22D72376B4DBC472E06C3BA403EE0A394DA63FC58D88686C611ABA98D6B3",
+                ),
+                (
+                    N - crypto_bigint::U256::ONE,
+                    b"Satoshi Nakamoto",
+                    "33A19B60E25FB6F4435AF53A3D42D493644827367E6453928554F43E49AA6F90",
+                ),
+                (
+                    u256("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181"),
+                    b"Alan Turing",
+                    "525A82B70E67874398067543FD84C83D30C175FDC45FDEEE082FE13B1D7CFDF1",
+                ),
+            ];
+
+            for (secret, message, k) in cases {
+                let private_key = PrivateKey::new(secret).unwrap();
+                let z = crypto_bigint::U256::from_be_slice(&sha256(message));
+
+                assert_eq!(private_key.deterministic_k(z), u256(k));
+            }
+        }
+
+        #[test]
+        fn low_s() {
+            use crate::{
+                hashes::sha256, private_key::PrivateKey, secp256k1::N, signature::Signature,
+            };
+
+            let cases: [(crypto_bigint::U256, &[u8], &str, &str); 2] = [
+                (
+                    u256("1"),
+                    b"Satoshi Nakamoto",
+                    "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8",
+                    "2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+                ),
+                (
+                    u256("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181"),
+                    b"Alan Turing",
+                    "7063ae83e7f62bbb171798131b4a0564b956930092b33b07b395615d9ec7e15c",
+                    "58dfcc1e00a35e1572f366ffe34ba0fc47db1e7189759b9fb233c5b05ab388ea",
+                ),
+            ];
+
+            for (secret, message, r, s) in cases {
+                let private_key = PrivateKey::new(secret).unwrap();
+                let z = crypto_bigint::U256::from_be_slice(&sha256(message));
+
+                let sig = private_key.sign(z).unwrap();
+                assert_eq!(sig, Signature::new(u256(r), u256(s)));
+                assert!(sig.is_low_s());
+                assert!(private_key.point().verify_strict(z, &sig));
+
+                let high_s = Signature::new(sig.r, N - sig.s);
+                assert!(!high_s.is_low_s());
+                assert!(private_key.point().verify(z, &high_s));
+                assert!(!private_key.point().verify_strict(z, &high_s));
+                assert_eq!(high_s.normalize(), sig);
+            }
+        }
+    }
+
+    mod chapter4 {
+        use super::util::u256;
+        use crate::{hex, private_key::PrivateKey, secp256k1::PublicKey};
+
+        #[test]
+        fn exercise1() {
+            let cases = [
+                (
+                    u256("1388"),
+                    "04ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c315dc72890a4f10a1481c031b03b351b0dc79901ca18a00cf009dbdb157a1d10",
+                ),
+                (
+                    u256("76e54a40efb620"),
+                    "04027f3da1918455e03c46f659266a1bb5204e959db7364d2f473bdf8f0a13cc9dff87647fd023c13b4a4994f17691895806e1b40b57f4fd22581a4f46851f3b06",
+                ),
+                (
+                    u256("deadbeef12345"),
+                    "04d90cd625ee87dd38656dd95cf79f65f60f7273b67d3096e68bd81e4f5342691f842efa762fd59961d0e99803c61edba8b3e3f7dc3a341836f97733aebf987121",
+                ),
+            ];
+
+            for (secret, expected) in cases {
+                let point = PrivateKey::new(secret).unwrap().point();
+                let actual = point.sec(false);
+
+                assert_eq!(hex::encode(&actual), expected);
+                assert_eq!(PublicKey::parse_sec(&actual).unwrap(), point);
+            }
+        }
+
+        #[test]
+        fn exercise2() {
+            let cases = [
+                (
+                    u256("1389"),
+                    "0357a4f368868a8a6d572991e484e664810ff14c05c0fa023275251151fe0e53d1",
+                ),
+                (
+                    u256("7730c781f7ae53"),
+                    "02933ec2d2b111b92737ec12f1c5d20f3233a0ad21cd8b36d0bca7a0cfa5cb8701",
+                ),
+                (
+                    u256("deadbeef54321"),
+                    "0296be5b1292f6c856b3c5654e886fc13511462059089cdf9c479623bfcbe77690",
+                ),
+            ];
+
+            for (secret, expected) in cases {
+                let point = PrivateKey::new(secret).unwrap().point();
+                let actual = point.sec(true);
+
+                assert_eq!(hex::encode(&actual), expected);
+                assert_eq!(PublicKey::parse_sec(&actual).unwrap(), point);
+            }
+        }
+
+        #[test]
+        fn parse_invalid_sec() {
+            // Invalid prefix
+            let sec =
+                hex::decode("0557a4f368868a8a6d572991e484e664810ff14c05c0fa023275251151fe0e53d1")
+                    .unwrap();
+            assert!(PublicKey::parse_sec(&sec).is_err());
+
+            // Wrong length for the prefix
+            let sec =
+                hex::decode("0457a4f368868a8a6d572991e484e664810ff14c05c0fa023275251151fe0e53d1")
+                    .unwrap();
+            assert!(PublicKey::parse_sec(&sec).is_err());
+
+            // x = 5 is not on the curve
+            let sec =
+                hex::decode("020000000000000000000000000000000000000000000000000000000000000005")
+                    .unwrap();
+            assert!(PublicKey::parse_sec(&sec).is_err());
+
+            // Point at infinity
+            assert!(PublicKey::parse_sec(&[0x00]).is_err());
+            assert!(PublicKey::parse_sec(&[]).is_err());
+        }
+
+        #[test]
+        fn hex_decode() {
+            assert_eq!(hex::decode("00ff7A").unwrap(), vec![0x00, 0xff, 0x7a]);
+
+            for invalid in ["0", "zz", "+f", "-f", "0x", " f"] {
+                assert!(hex::decode(invalid).is_err());
+            }
+        }
+
+        #[test]
+        fn exercise3() {
+            use crate::signature::Signature;
+
+            let sig = Signature::new(
+                u256("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6"),
+                u256("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec"),
+            );
+            let expected = "3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec";
+
+            let actual = sig.der();
+
+            assert_eq!(hex::encode(&actual), expected);
+            assert_eq!(Signature::parse_der(&actual).unwrap(), sig);
+
+            let sig = Signature::new(u256("1"), u256("80"));
+            let actual = sig.der();
+
+            assert_eq!(hex::encode(&actual), "300702010102020080");
+            assert_eq!(Signature::parse_der(&actual).unwrap(), sig);
+        }
+
+        #[test]
+        fn parse_der_strict() {
+            use crate::{signature::Signature, Error};
+
+            let malformed = [
+                // Too short
+                "30050201010201",
+                // Wrong compound marker
+                "3106020101020101",
+                // Length does not match
+                "3007020101020101",
+                // Wrong integer marker
+                "3006030101020101",
+                // Zero length r
+                "3006020002020101",
+                // r length past the end
+                "3006020501020101",
+                // Trailing bytes
+                "300602010102010100",
+            ];
+            for der in malformed {
+                let der = hex::decode(der).unwrap();
+                assert!(
+                    matches!(Signature::parse_der(&der), Err(Error::InvalidDer(_))),
+                    "{der:?}"
+                );
+            }
+
+            let non_canonical = [
+                // Excess padding on r
+                "300702020001020101",
+                // Negative r
+                "3006020181020101",
+                // Excess padding on s
+                "300702010102020001",
+                // Negative s
+                "3006020101020181",
+            ];
+            for der in non_canonical {
+                let der = hex::decode(der).unwrap();
+                assert!(
+                    matches!(Signature::parse_der(&der), Err(Error::NonCanonicalDer(_))),
+                    "{der:?}"
+                );
+            }
+        }
+
+        #[test]
+        fn exercise4() {
+            let cases = [
+                (
+                    "7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+                    "9MA8fRQrT4u8Zj8ZRd6MAiiyaxb2Y1CMpvVkHQu5hVM6",
+                ),
+                (
+                    "eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c",
+                    "4fE3H2E6XMp4SsxtwinF7w9a34ooUrwWe4WsW1458Pd",
+                ),
+                (
+                    "c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab6",
+                    "EQJsjkd6JaGwxrjEhfeqPenqHwrBmPQZjJGNSCHBkcF7",
+                ),
+                ("00000001", "1112"),
+                ("", ""),
+            ];
+
+            for (bytes, expected) in cases {
+                let bytes = hex::decode(bytes).unwrap();
+
+                assert_eq!(crate::base58::encode(&bytes), expected);
+                assert_eq!(crate::base58::decode(expected).unwrap(), bytes);
+            }
+
+            assert!(matches!(
+                crate::base58::decode("9MA8fRQrT4u8Zj8ZRd6MAiiyaxb2Y1CMpvVkHQu5hVM0"),
+                Err(crate::Error::InvalidBase58(_))
+            ));
+        }
+
+        #[test]
+        fn base58_check() {
+            use crate::base58::{decode_check, encode_check};
+
+            // The address of the genesis block coinbase output
+            let address = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+            let payload = hex::decode("0062e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+
+            assert_eq!(decode_check(address).unwrap(), payload);
+            assert_eq!(encode_check(&payload), address);
+
+            assert!(matches!(
+                decode_check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+                Err(crate::Error::ChecksumMismatch(_))
+            ));
+            assert!(matches!(
+                decode_check("1"),
+                Err(crate::Error::InvalidBase58(_))
+            ));
+        }
+
+        #[test]
+        fn exercise5() {
+            use crate::Network;
+
+            let cases = [
+                (
+                    u256("138a"),
+                    false,
+                    Network::Testnet,
+                    "mmTPbXQFxboEtNRkwfh6K51jvdtHLxGeMA",
+                ),
+                (
+                    u256("777c6b16216400"),
+                    true,
+                    Network::Testnet,
+                    "mopVkxp8UhXqRYbCYJsbeE1h1fiF64jcoH",
+                ),
+                (
+                    u256("12345deadbeef"),
+                    true,
+                    Network::Mainnet,
+                    "1F1Pn2y6pDb68E5nYJJeba4TLg2U7B6KF1",
+                ),
+            ];
+
+            for (secret, compressed, network, expected) in cases {
+                let point = PrivateKey::new(secret).unwrap().point();
+
+                assert_eq!(point.address(compressed, network), expected);
+            }
+        }
+
+        #[test]
+        fn exercise6() {
+            use crate::Network;
+
+            let cases = [
+                (
+                    u256("138b"),
+                    true,
+                    Network::Testnet,
+                    "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN8rFTv2sfUK",
+                ),
+                (
+                    u256("77c8350c02b595"),
+                    false,
+                    Network::Testnet,
+                    "91avARGdfge8E4tZfYLoxeJ5sGBdNJQH4kvjpWAxgzczjbCwxic",
+                ),
+                (
+                    u256("54321deadbeef"),
+                    true,
+                    Network::Mainnet,
+                    "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgiuQJv1h8Ytr2S53a",
+                ),
+            ];
+
+            for (secret, compressed, network, expected) in cases {
+                let private_key = PrivateKey::new(secret).unwrap();
+
+                assert_eq!(private_key.wif(compressed, network), expected);
+                assert_eq!(
+                    PrivateKey::parse_wif(expected).unwrap(),
+                    (private_key, compressed, network)
+                );
+            }
+        }
+
+        #[test]
+        fn parse_invalid_wif() {
+            use crate::base58;
+
+            let mut payload = vec![0x80];
+            payload.extend_from_slice(&u256("54321deadbeef").to_be_bytes());
+
+            // Unknown prefix
+            payload[0] = 0x81;
+            assert!(PrivateKey::parse_wif(&base58::encode_check(&payload)).is_err());
+            payload[0] = 0x80;
+
+            // Invalid compression suffix
+            let mut invalid = payload.clone();
+            invalid.push(0x02);
+            assert!(PrivateKey::parse_wif(&base58::encode_check(&invalid)).is_err());
+
+            // Truncated secret
+            assert!(PrivateKey::parse_wif(&base58::encode_check(&payload[..32])).is_err());
+
+            // Bad checksum
+            assert!(matches!(
+                PrivateKey::parse_wif("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgiuQJv1h8Ytr2S53b"),
+                Err(crate::Error::ChecksumMismatch(_))
+            ));
+        }
+    }
+
+    mod chapter5 {
+        use crate::{hex, tx::Tx, varint};
+
+        pub(super) const TX: &str = "0100000001813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762dd5423e332166702cb75f40df79fea1288ac19430600";
+
+        #[test]
+        fn varint() {
+            let cases: [(u64, &str); 5] = [
+                (0x6a, "6a"),
+                (0xfd, "fdfd00"),
+                (0x1234, "fd3412"),
+                (0x12345678, "fe78563412"),
+                (0x123456789a, "ff9a78563412000000"),
+            ];
+
+            for (value, expected) in cases {
+                let encoded = varint::encode(value);
+
+                assert_eq!(hex::encode(&encoded), expected);
+                assert_eq!(varint::read(&mut encoded.as_slice()).unwrap(), value);
+            }
+        }
+
+        #[test]
+        fn parse() {
+            let bytes = hex::decode(TX).unwrap();
+            let tx = Tx::parse(&mut bytes.as_slice()).unwrap();
+
+            assert_eq!(tx.version, 1);
+
+            assert_eq!(tx.tx_ins.len(), 1);
+            assert_eq!(
+                hex::encode(&tx.tx_ins[0].prev_tx),
+                "d1c789a9c60383bf715f3f6ad9d14b91fe55f3deb369fe5d9280cb1a01793f81"
+            );
+            assert_eq!(tx.tx_ins[0].prev_index, 0);
+            assert_eq!(hex::encode(&tx.tx_ins[0].script_sig.raw_serialize()), "483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278a");
+            assert_eq!(tx.tx_ins[0].sequence, 0xfffffffe);
+
+            assert_eq!(tx.tx_outs.len(), 2);
+            assert_eq!(tx.tx_outs[0].amount, 32454049);
+            assert_eq!(
+                hex::encode(&tx.tx_outs[0].script_pubkey.raw_serialize()),
+                "76a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac"
+            );
+            assert_eq!(tx.tx_outs[1].amount, 10011545);
+            assert_eq!(
+                hex::encode(&tx.tx_outs[1].script_pubkey.raw_serialize()),
+                "76a9141c4bc762dd5423e332166702cb75f40df79fea1288ac"
+            );
+
+            assert_eq!(tx.locktime, 410393);
+        }
+
+        #[test]
+        fn round_trip() {
+            let cases = [
+                (
+                    TX,
+                    "452c629d67e41baec3ac6f04fe744b4b9617f8f859c63b3002f8684e7a4fee03",
+                ),
+                (
+                    "01000000010c7196428403d8b0c88fcb3ee8d64f56f55c8973c9ab7dd106bb4f3527f5888d000000006a4730440220503a696f55f2c00eee2ac5e65b17767cd88ed04866b5637d3c1d5d996a70656d02202c9aff698f343abb6d176704beda63fcdec503133ea4f6a5216b7f925fa9910c0121024d89b5a13d6521388969209df27a8469bd565aff10e8d42cef931fad5121bfb8ffffffff02b825b404000000001976a914ef79e7ee9fff98bcfd08473d2b76b02a48f8c69088ac0000000000000000296a2732363030393438363937313732333132373633313032313332353630353838373931323132373000000000",
+                    "971ed48a62c143bbd9c87f4bafa2ef213cfa106c6e140f111931d0be307468dd",
+                ),
+            ];
+
+            for (tx_hex, id) in cases {
+                let bytes = hex::decode(tx_hex).unwrap();
+                let tx = Tx::parse(&mut bytes.as_slice()).unwrap();
+
+                assert_eq!(hex::encode(&tx.serialize()), tx_hex);
+                assert_eq!(tx.id(), id);
+            }
+        }
+
+        #[test]
+        fn parse_truncated() {
+            let bytes = hex::decode(TX).unwrap();
+
+            assert!(matches!(
+                Tx::parse(&mut &bytes[..bytes.len() - 1]),
+                Err(crate::Error::Io(_))
+            ));
+            assert!(Tx::parse(&mut &bytes[..50]).is_err());
+        }
+
+        #[test]
+        fn non_minimal_scripts() {
+            use crate::hashes::hash256;
+
+            let p2pkh = "1976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac";
+            // The same hash pushed with OP_PUSHDATA1, and a script ending in a truncated push
+            for script in [
+                "1a76a94c14bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac",
+                "024c05",
+            ] {
+                let tx_hex = TX.replace(p2pkh, script);
+                let bytes = hex::decode(&tx_hex).unwrap();
+                let tx = Tx::parse(&mut bytes.as_slice()).unwrap();
+
+                assert_eq!(hex::encode(&tx.serialize()), tx_hex);
+
+                let mut hash = hash256(&bytes);
+                hash.reverse();
+                assert_eq!(tx.id(), hex::encode(&hash));
+
+                assert_eq!(tx.tx_outs[0].script_pubkey.p2pkh_hash(), None);
+            }
+        }
+    }
+
+    mod chapter6 {
+        use crypto_bigint::U256;
+
+        use crate::{
+            hex,
+            script::{self, Command, Opcode, Script, ScriptError},
+        };
+
+        fn ops(ops: &[Opcode]) -> Script {
+            Script::new(ops.iter().map(|op| Command::Op(*op)).collect())
+        }
+
+        fn data(hex: &str) -> Command {
+            Command::Data(hex::decode(hex).unwrap())
+        }
+
+        #[test]
+        fn parse() {
+            let bytes = hex::decode("6a47304402207899531a52d59a6de200179928ca900254a36b8dff8bb75f5f5d71b1cdc26125022008b422690b8461cb52c3cc30330b23d574351872b7c361e9aae3649071c1a7160121035d5c93d9ac96881f19ba1f686f15f009ded7c62efe85a872e6a19b43c15a2937").unwrap();
+            let script = Script::parse(&mut bytes.as_slice()).unwrap();
+
+            assert_eq!(
+                script.cmds().unwrap(),
+                vec![
+                    Command::Data(hex::decode("304402207899531a52d59a6de200179928ca900254a36b8dff8bb75f5f5d71b1cdc26125022008b422690b8461cb52c3cc30330b23d574351872b7c361e9aae3649071c1a71601").unwrap()),
+                    Command::Data(hex::decode("035d5c93d9ac96881f19ba1f686f15f009ded7c62efe85a872e6a19b43c15a2937").unwrap()),
+                ]
+            );
+            assert_eq!(script.serialize(), bytes);
+        }
+
+        #[test]
+        fn display() {
+            let bytes = hex::decode("76a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac").unwrap();
+            let script = Script::parse_raw(&bytes).unwrap();
+
+            assert_eq!(
+                script.to_string(),
+                "OP_DUP OP_HASH160 bc3b654dca7e56b04dca18f2566cdaf02e8d9ada OP_EQUALVERIFY OP_CHECKSIG"
+            );
+            assert_eq!(script.raw_serialize(), bytes);
+        }
+
+        #[test]
+        fn pushdata() {
+            for (len, prefix) in [
+                (75, "4b"),
+                (76, "4c4c"),
+                (255, "4cff"),
+                (256, "4d0001"),
+                (520, "4d0802"),
+                (65536, "4e00000100"),
+            ] {
+                let script = Script::new(vec![Command::Data(vec![0xab; len])]);
+                let raw = script.raw_serialize();
+
+                assert_eq!(hex::encode(&raw[..prefix.len() / 2]), prefix);
+                assert_eq!(raw.len(), prefix.len() / 2 + len);
+                assert_eq!(Script::parse_raw(&raw).unwrap(), script);
+            }
+
+            let script = Script::parse_raw(&hex::decode("4e03000000abcdef").unwrap()).unwrap();
+            assert_eq!(
+                script.cmds().unwrap(),
+                vec![Command::Data(vec![0xab, 0xcd, 0xef])]
+            );
+        }
+
+        #[test]
+        fn opcodes() {
+            for byte in 0..=u8::MAX {
+                match Opcode::from_byte(byte) {
+                    Some(op) => assert_eq!(op.to_byte(), byte),
+                    None => assert!((0x01..=0x4b).contains(&byte)),
+                }
+            }
+
+            assert_eq!(Opcode::from_byte(0xba), Some(Opcode::OP_CHECKSIGADD));
+            assert_eq!(Opcode::OP_CHECKMULTISIG.name(), "OP_CHECKMULTISIG");
+            assert_eq!(Opcode::from_byte(0xbb).unwrap().name(), "OP_UNKNOWN_0xbb");
+        }
+
+        #[test]
+        fn parse_invalid() {
+            for script in ["05abcd", "4c", "4d01", "4c02ab", "4e0100"] {
+                assert!(Script::parse_raw(&hex::decode(script).unwrap()).is_err());
+            }
+
+            assert!(Script::parse(&mut hex::decode("0376a9").unwrap().as_slice()).is_err());
+        }
+
+        #[test]
+        fn find_and_delete() {
+            // From Bitcoin Core's script_FindAndDelete test
+            let cases = [
+                ("5152", "52", "51"),
+                ("0302ff030302ff03", "0302ff03", ""),
+                ("0302ff030302ff03", "02", "0302ff030302ff03"),
+                ("0302ff030302ff03", "ff", "0302ff030302ff03"),
+                // Removing the push-three-bytes prefix leaves push-two-bytes commands
+                ("0302ff030302ff03", "03", "02ff0302ff03"),
+                // Byte sequences spanning several commands
+                ("02feed5169", "feed51", "02feed5169"),
+                ("02feed5169", "02feed51", "69"),
+                ("516902feed5169", "02feed51", "516969"),
+            ];
+
+            for (script, pattern, expected) in cases {
+                let script = Script::from_raw(hex::decode(script).unwrap());
+                let pattern = Script::from_raw(hex::decode(pattern).unwrap());
+
+                assert_eq!(
+                    hex::encode(&script.find_and_delete(&pattern).raw_serialize()),
+                    expected
+                );
+            }
+        }
+
+        #[test]
+        fn non_minimal_push() {
+            let raw = hex::decode("4c0101").unwrap();
+            let script = Script::parse_raw(&raw).unwrap();
+
+            assert_eq!(script.raw_serialize(), raw);
+            assert_eq!(script.cmds().unwrap(), vec![data("01")]);
+            assert_ne!(script, Script::new(vec![data("01")]));
+
+            // Consensus only accepts direct pushes as witness programs
+            let program = hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+            let pushdata = hex::decode("004c14751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+            assert!(Script::from_raw(program).witness_program().is_some());
+            assert_eq!(Script::from_raw(pushdata).witness_program(), None);
+
+            // Scripts that do not decode are kept, and fail when run
+            let truncated = Script::from_raw(hex::decode("514c05").unwrap());
+            assert!(truncated.cmds().is_err());
+            assert_eq!(truncated.to_string(), "OP_1 [error]");
+            assert!(matches!(
+                truncated.evaluate(U256::ZERO),
+                Err(crate::Error::Script {
+                    opcode: None,
+                    error: ScriptError::BadOpcode
+                })
+            ));
+        }
+
+        #[test]
+        fn num() {
+            let cases = [
+                (0, ""),
+                (1, "01"),
+                (-1, "81"),
+                (127, "7f"),
+                (128, "8000"),
+                (-128, "8080"),
+                (255, "ff00"),
+                (256, "0001"),
+                (-0x7fffffff, "ffffffff"),
+            ];
+
+            for (num, expected) in cases {
+                assert_eq!(hex::encode(&script::encode_num(num)), expected);
+                assert_eq!(script::decode_num(&hex::decode(expected).unwrap()), num);
+            }
+        }
+
+        #[test]
+        fn evaluate() {
+            use Opcode::*;
+
+            let cases = [
+                ops(&[OP_2, OP_3, OP_ADD, OP_5, OP_EQUAL]),
+                ops(&[OP_2, OP_DUP, OP_DUP, OP_ADD, OP_ADD, OP_6, OP_NUMEQUAL]),
+                ops(&[
+                    OP_1,
+                    OP_2,
+                    OP_3,
+                    OP_ROT,
+                    OP_1,
+                    OP_EQUALVERIFY,
+                    OP_DEPTH,
+                    OP_2,
+                    OP_EQUAL,
+                ]),
+                ops(&[OP_1, OP_2, OP_SWAP, OP_1SUB, OP_NOT]),
+                ops(&[OP_3, OP_2, OP_5, OP_WITHIN]),
+                ops(&[OP_1, OP_IF, OP_2, OP_ELSE, OP_3, OP_ENDIF, OP_2, OP_EQUAL]),
+                ops(&[
+                    OP_0, OP_NOTIF, OP_0, OP_IF, OP_RETURN, OP_ENDIF, OP_1, OP_ENDIF,
+                ]),
+                ops(&[OP_0, OP_IF, OP_RESERVED, OP_ELSE, OP_1, OP_ENDIF]),
+                ops(&[
+                    OP_1,
+                    OP_2,
+                    OP_3,
+                    OP_4,
+                    OP_2SWAP,
+                    OP_2DROP,
+                    OP_4,
+                    OP_NUMEQUAL,
+                ]),
+                ops(&[OP_1, OP_2, OP_TOALTSTACK, OP_DROP, OP_FROMALTSTACK]),
+                Script::new(vec![
+                    Command::Data(Vec::new()),
+                    Command::Op(OP_SHA256),
+                    data("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+                    Command::Op(OP_EQUAL),
+                ]),
+            ];
+
+            for script in cases {
+                assert!(script.evaluate(U256::ZERO).is_ok(), "{script}");
+            }
+        }
+
+        #[test]
+        fn evaluate_errors() {
+            use Opcode::*;
+
+            let cases = [
+                (
+                    ops(&[OP_1, OP_2, OP_EQUALVERIFY]),
+                    Some(OP_EQUALVERIFY),
+                    ScriptError::VerifyFailed,
+                ),
+                (
+                    ops(&[OP_1, OP_ADD]),
+                    Some(OP_ADD),
+                    ScriptError::StackUnderflow,
+                ),
+                (
+                    ops(&[OP_1, OP_RETURN]),
+                    Some(OP_RETURN),
+                    ScriptError::OpReturn,
+                ),
+                (
+                    ops(&[OP_0, OP_IF, OP_CAT, OP_ENDIF, OP_1]),
+                    Some(OP_CAT),
+                    ScriptError::DisabledOpcode,
+                ),
+                (
+                    ops(&[OP_1, OP_RESERVED]),
+                    Some(OP_RESERVED),
+                    ScriptError::BadOpcode,
+                ),
+                (
+                    ops(&[OP_1, OP_ENDIF]),
+                    Some(OP_ENDIF),
+                    ScriptError::UnbalancedConditional,
+                ),
+                (
+                    ops(&[OP_1, OP_IF, OP_1]),
+                    None,
+                    ScriptError::UnbalancedConditional,
+                ),
+                (ops(&[OP_1, OP_2, OP_EQUAL]), None, ScriptError::EvalFalse),
+                (ops(&[]), None, ScriptError::EvalFalse),
+                (
+                    Script::new(vec![data("0000000001"), Command::Op(OP_1ADD)]),
+                    Some(OP_1ADD),
+                    ScriptError::NumberOverflow,
+                ),
+            ];
+
+            for (script, expected_opcode, expected_error) in cases {
+                match script.evaluate(U256::ZERO) {
+                    Err(crate::Error::Script { opcode, error }) => {
+                        assert_eq!(opcode, expected_opcode, "{script}");
+                        assert_eq!(error, expected_error, "{script}");
+                    }
+                    result => panic!("{script}: {result:?}"),
+                }
+            }
+        }
+
+        #[test]
+        fn p2pk() {
+            let z = U256::from_be_hex(
+                "7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+            );
+            let script_pubkey = Script::new(vec![
+                data("04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34"),
+                Command::Op(Opcode::OP_CHECKSIG),
+            ]);
+            let script_sig = Script::new(vec![data("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601")]);
+
+            let combined = script_sig + script_pubkey;
+
+            assert!(combined.evaluate(z).is_ok());
+            assert!(combined.evaluate(z.wrapping_add(&U256::ONE)).is_err());
+        }
+
+        #[test]
+        fn multisig() {
+            let z = U256::from_be_hex(
+                "e71bfa115715d6fd33796948126f40a8cdd39f187e4afb03896795189fe1423c",
+            );
+            let sig1 = data("3045022100dc92655fe37036f47756db8102e0d7d5e28b3beb83a8fef4f5dc0559bddfb94e02205a36d4e4e6c7fcd16658c50783e00c341609977aed3ad00937bf4ee942a8993701");
+            let sig2 = data("3045022100da6bee3c93766232079a01639d07fa869598749729ae323eab8eef53577d611b02207bef15429dcadce2121ea07f233115c6f09034c0be68db99980b9a6c5e75402201");
+            let sec1 = data("022626e955ea6ea6d98850c994f9107b036b1334f18ca8830bfff1295d21cfdb70");
+            let sec2 = data("03b287eaf122eea69030a0e9feed096bed8045c8b98bec453e1ffac7fbdbd4bb71");
+
+            let script_pubkey = Script::new(vec![
+                Command::Op(Opcode::OP_2),
+                sec1,
+                sec2,
+                Command::Op(Opcode::OP_2),
+                Command::Op(Opcode::OP_CHECKMULTISIG),
+            ]);
+
+            let valid = Script::new(vec![Command::Op(Opcode::OP_0), sig1.clone(), sig2.clone()]);
+            assert!((valid + script_pubkey.clone()).evaluate(z).is_ok());
+
+            // Out of order
+            let swapped = Script::new(vec![Command::Op(Opcode::OP_0), sig2, sig1]);
+            assert!((swapped + script_pubkey).evaluate(z).is_err());
+        }
+    }
+
+    mod chapter7 {
+        use crypto_bigint::U256;
+
+        use super::{chapter5::TX, util::parse_tx};
+        use crate::{
+            hex,
+            private_key::PrivateKey,
+            script::{Command, Opcode, Script},
+            tx::{self, MemoryUtxoProvider, Tx, TxIn, TxOut},
+            tx_builder::{self, TxBuilder},
+            Network,
+        };
+
+        /// The output spent by the only input of `TX`.
+        fn utxos() -> MemoryUtxoProvider {
+            let prev_tx =
+                hex::decode("d1c789a9c60383bf715f3f6ad9d14b91fe55f3deb369fe5d9280cb1a01793f81")
+                    .unwrap();
+            let script_pubkey = Script::parse_raw(
+                &hex::decode("76a914a802fc56c704ce87c42d7c92eb75e7896bdc41ae88ac").unwrap(),
+            )
+            .unwrap();
+
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert(
+                prev_tx.try_into().unwrap(),
+                0,
+                TxOut::new(42505594, script_pubkey),
+            );
+            utxos
+        }
+
+        #[test]
+        fn sig_hash() {
+            let tx = parse_tx(TX);
+            let script_pubkey = Script::parse_raw(
+                &hex::decode("76a914a802fc56c704ce87c42d7c92eb75e7896bdc41ae88ac").unwrap(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                tx.sig_hash(0, &script_pubkey, tx::SIGHASH_ALL),
+                U256::from_be_hex(
+                    "27e0c5994dec7824e56dec6b2fcb342eb7cdb0d0957c2fce9882f715e85d81a6"
+                )
+            );
+        }
+
+        #[test]
+        fn sig_hash_types() {
+            // From Bitcoin Core's sighash.json, hashes in display order
+            let cases = [
+                ("50818f4c01b464538b1e7e7f5ae4ed96ad23c68c830e78da9a845bc19b5c3b0b20bb82e5e9030000000763526a63655352ffffffff023b3f9c040000000008630051516a6a5163a83caf01000000000553ab65510000000000", "6aac", 0, 946795545, "746306f322de2b4b58ffe7faae83f6a72433c22f88062cdde881d4dd8a5a4e2d"),
+                ("73107cbd025c22ebc8c3e0a47b2a760739216a528de8d4dab5d45cbeb3051cebae73b01ca10200000007ab6353656a636affffffffe26816dffc670841e6a6c8c61c586da401df1261a330a6c6b3dd9f9a0789bc9e000000000800ac6552ac6aac51ffffffff0174a8f0010000000004ac52515100000000", "5163ac63635151ac", 1, 1190874345, "06e328de263a87b09beabe222a21627a6ea5c7f560030da31610c4611f4a46bc"),
+                ("97be4f7702dc20b087a1fdd533c7de762a3f2867a8f439bddf0dcec9a374dfd0276f9c55cc0300000000cdfb1dbe6582499569127bda6ca4aaff02c132dc73e15dcd91d73da77e92a32a13d1a0ba0200000002ab51ffffffff048cfbe202000000000900516351515363ac535128ce0100000000076aac5365ab6aabc84e8302000000000863536a53ab6a6552f051230500000000066aac535153510848d813", "ac51", 0, 229541474, "e5da9a416ea883be1f8b8b2d178463633f19de3fa82ae25d44ffb531e35bdbc8"),
+                ("ceecfa6c02b7e3345445b82226b15b7a097563fa7d15f3b0c979232b138124b62c0be007890200000009abac51536a63525253ffffffffbae481ccb4f15d94db5ec0d8854c24c1cc8642bd0c6300ede98a91ca13a4539a0200000001ac50b0813d023110f5020000000006acabac526563e2b0d0040000000009656aac0063516a536300000000", "0063526500", 0, -1862053821, "e1600e6df8a6160a79ac32aa40bb4644daa88b5f76c0d7d13bf003327223f70c"),
+                ("d3b7421e011f4de0f1cea9ba7458bf3486bee722519efab711a963fa8c100970cf7488b7bb0200000003525352dcd61b300148be5d05000000000000000000", "535251536aac536a", 0, -1960128125, "29aa6d2d752d3310eba20442770ad345b7f6a35f96161ede5f07b33e92053e2a"),
+            ];
+
+            for (tx_hex, script, input_index, sighash_type, expected) in cases {
+                let tx = parse_tx(tx_hex);
+                let script = Script::parse_raw(&hex::decode(script).unwrap()).unwrap();
+
+                let mut expected = hex::decode(expected).unwrap();
+                expected.reverse();
+
+                assert_eq!(
+                    tx.sig_hash(input_index, &script, sighash_type as u32),
+                    U256::from_be_slice(&expected)
+                );
+            }
+        }
+
+        #[test]
+        fn verify() {
+            let tx = parse_tx(TX);
+            let utxos = utxos();
+
+            assert_eq!(tx.fee(&utxos).unwrap(), 40000);
+            assert!(tx.verify(&utxos).is_ok());
+
+            let mut tampered = tx.clone();
+            tampered.tx_outs[0].amount -= 1;
+            assert!(matches!(
+                tampered.verify(&utxos),
+                Err(crate::Error::Script { .. })
+            ));
+
+            let mut overspent = tx.clone();
+            overspent.tx_outs[0].amount += 40001;
+            assert!(matches!(
+                overspent.verify(&utxos),
+                Err(crate::Error::ValueError(_))
+            ));
+
+            assert!(matches!(
+                tx.verify(&MemoryUtxoProvider::new()),
+                Err(crate::Error::MissingUtxo(_))
+            ));
+        }
+
+        #[test]
+        fn fee_overflow() {
+            let tx = parse_tx(TX);
+            let utxos = utxos();
+
+            // Outputs summing past u64::MAX
+            let mut overflowing = tx.clone();
+            overflowing.tx_outs[0].amount = u64::MAX;
+            assert!(matches!(
+                overflowing.fee(&utxos),
+                Err(crate::Error::ValueError(_))
+            ));
+
+            // Outputs summing past the 21 million bitcoin
+            let mut too_large = tx.clone();
+            too_large.tx_outs[0].amount = tx::MAX_MONEY;
+            assert!(matches!(
+                too_large.fee(&utxos),
+                Err(crate::Error::ValueError(_))
+            ));
+
+            // Inputs summing past u64::MAX
+            let mut inputs = tx.clone();
+            inputs.tx_ins.push(inputs.tx_ins[0].clone());
+            let mut utxos = MemoryUtxoProvider::new();
+            let prev_out = TxOut::new(u64::MAX, Script::default());
+            utxos.insert(inputs.tx_ins[0].prev_tx, 0, prev_out);
+            assert!(matches!(
+                inputs.fee(&utxos),
+                Err(crate::Error::ValueError(_))
+            ));
+        }
+
+        #[test]
+        fn sighash_single_bug() {
+            let tx = Tx {
+                version: 1,
+                tx_ins: vec![TxIn::new([0; 32], 0), TxIn::new([1; 32], 0)],
+                tx_outs: vec![TxOut::new(0, Script::default())],
+                locktime: 0,
+            };
+
+            let mut one = [0u8; 32];
+            one[0] = 1;
+
+            assert_eq!(
+                tx.sig_hash(1, &Script::default(), tx::SIGHASH_SINGLE),
+                U256::from_be_slice(&one)
+            );
+            assert_ne!(
+                tx.sig_hash(0, &Script::default(), tx::SIGHASH_SINGLE),
+                U256::from_be_slice(&one)
+            );
+        }
+
+        #[test]
+        fn code_separator() {
+            use crate::script::{Command::*, Opcode::*};
+
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+            let sec = private_key.point().sec(true);
+            let sign = |tx: &Tx, script_code: &Script| {
+                let z = tx.sig_hash(0, script_code, tx::SIGHASH_ALL);
+                let mut sig = private_key.sign(z).unwrap().der();
+                sig.push(tx::SIGHASH_ALL as u8);
+                sig
+            };
+
+            let prev_tx = [0xab; 32];
+            let mut tx = Tx {
+                version: 1,
+                tx_ins: vec![TxIn::new(prev_tx, 0)],
+                tx_outs: vec![TxOut::new(90_000, Script::default())],
+                locktime: 0,
+            };
+
+            // The first signature commits to the whole script, the second only to the part after
+            // OP_CODESEPARATOR
+            let after_separator = Script::new(vec![Data(sec.clone()), Op(OP_CHECKSIG)]);
+            let script_pubkey = Script::new(vec![
+                Data(sec.clone()),
+                Op(OP_CHECKSIGVERIFY),
+                Op(OP_CODESEPARATOR),
+            ]) + after_separator.clone();
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert(prev_tx, 0, TxOut::new(100_000, script_pubkey.clone()));
+
+            let first = sign(&tx, &script_pubkey);
+            let second = sign(&tx, &after_separator);
+
+            tx.tx_ins[0].script_sig = Script::new(vec![Data(second), Data(first.clone())]);
+            assert!(tx.verify_input(0, &utxos).is_ok());
+
+            tx.tx_ins[0].script_sig = Script::new(vec![Data(first.clone()), Data(first)]);
+            assert!(tx.verify_input(0, &utxos).is_err());
+
+            // A signature found in the script it signs is removed before hashing (FindAndDelete)
+            let script_code = Script::new(vec![Op(OP_DROP), Data(sec), Op(OP_CHECKSIG)]);
+            tx.tx_ins[0] = TxIn::new(prev_tx, 1);
+            let sig = sign(&tx, &script_code);
+            let script_pubkey = Script::new(vec![Data(sig.clone())]) + script_code;
+            utxos.insert(prev_tx, 1, TxOut::new(100_000, script_pubkey));
+
+            tx.tx_ins[0].script_sig = Script::new(vec![Data(sig)]);
+            assert!(tx.verify_input(0, &utxos).is_ok());
+        }
+
+        #[test]
+        fn sign_low_r() {
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+
+            for z in [1u64, 2, 3, 4] {
+                let z = U256::from_u64(z);
+                let sig = private_key.sign_low_r(z).unwrap();
+
+                assert!(sig.is_low_r());
+                assert!(sig.der().len() <= 71);
+                assert!(private_key.point().verify(z, &sig));
+            }
+        }
+
+        #[test]
+        fn tx_builder() {
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+            let address = private_key.point().address(true, Network::Testnet);
+            let destination = "mwJn1YPMq7y5F8J3LkC5Hxg9PHyZ5K4cFv";
+
+            let prev_tx = [0xab; 32];
+            let utxo = TxOut::new(100_000, Script::p2pkh(&private_key.point().hash160(true)));
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert(prev_tx, 1, utxo.clone());
+
+            let builder = TxBuilder::new(2, &address).add_utxo(prev_tx, 1, utxo, private_key);
+
+            let tx = builder
+                .clone()
+                .add_output(destination, 60_000)
+                .build()
+                .unwrap();
+            assert_eq!(tx.version, 2);
+            assert_eq!(tx.tx_outs.len(), 2);
+            assert_eq!(
+                tx.tx_outs[0].script_pubkey,
+                Script::from_address(destination).unwrap()
+            );
+            assert_eq!(
+                tx.tx_outs[1].script_pubkey,
+                Script::from_address(&address).unwrap()
+            );
+            assert!(tx.verify(&utxos).is_ok());
+
+            // 2 sat/byte
+            let size = tx.serialize().len() as u64;
+            assert_eq!(tx.fee(&utxos).unwrap(), 2 * size);
+
+            // Change below the dust limit is left as fee
+            let tx = builder
+                .clone()
+                .add_output(destination, 99_200)
+                .build()
+                .unwrap();
+            assert_eq!(tx.tx_outs.len(), 1);
+            assert_eq!(tx.fee(&utxos).unwrap(), 800);
+
+            assert!(builder
+                .clone()
+                .add_output(destination, 99_700)
+                .build()
+                .is_err());
+            assert!(builder
+                .add_output("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3", 1_000)
+                .build()
+                .is_err());
+        }
+
+        #[test]
+        fn tx_builder_empty() {
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+            let address = private_key.point().address(true, Network::Testnet);
+            let script_pubkey = Script::p2pkh(&private_key.point().hash160(true));
+
+            // No inputs, even when nothing needs to be paid
+            assert!(TxBuilder::new(0, &address).build().is_err());
+            assert!(TxBuilder::new(0, &address)
+                .add_output(&address, 0)
+                .build()
+                .is_err());
+
+            // Sweeping to the change address leaves a single output, unless the change is dust
+            let sweep = |amount| {
+                TxBuilder::new(2, &address)
+                    .add_utxo(
+                        [0xab; 32],
+                        1,
+                        TxOut::new(amount, script_pubkey.clone()),
+                        private_key,
+                    )
+                    .build()
+            };
+            assert_eq!(sweep(1_000).unwrap().tx_outs.len(), 1);
+            assert!(sweep(800).is_err());
+        }
+
+        #[test]
+        fn tx_builder_overflow() {
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+            let address = private_key.point().address(true, Network::Testnet);
+            let utxo = TxOut::new(100_000, Script::p2pkh(&private_key.point().hash160(true)));
+
+            let builder = TxBuilder::new(2, &address)
+                .add_utxo([0xab; 32], 1, utxo.clone(), private_key)
+                .add_output(&address, 50_000);
+
+            assert!(builder
+                .clone()
+                .add_utxo(
+                    [0xab; 32],
+                    2,
+                    TxOut::new(u64::MAX, utxo.script_pubkey.clone()),
+                    private_key
+                )
+                .build()
+                .is_err());
+            assert!(builder
+                .clone()
+                .add_output(&address, u64::MAX - 1_000)
+                .build()
+                .is_err());
+            assert!(TxBuilder::new(u64::MAX / 2, &address)
+                .add_utxo([0xab; 32], 1, utxo, private_key)
+                .build()
+                .is_err());
+        }
+
+        #[test]
+        fn dust_threshold() {
+            let hash = [0x11; 20];
+
+            assert_eq!(tx_builder::dust_threshold(&Script::p2pkh(&hash)), 546);
+            assert_eq!(tx_builder::dust_threshold(&Script::p2sh(&hash)), 540);
+            assert_eq!(tx_builder::dust_threshold(&Script::p2wpkh(&hash)), 294);
+            assert_eq!(tx_builder::dust_threshold(&Script::p2wsh(&[0x11; 32])), 330);
+            assert_eq!(
+                tx_builder::dust_threshold(&Script::new(vec![Command::Op(Opcode::OP_RETURN)])),
+                0
+            );
+        }
+    }
+
+    mod chapter8 {
+        use crypto_bigint::U256;
+
+        use crate::{
+            hex,
+            script::{Command, Opcode, Script, ScriptError},
+            secp256k1::PublicKey,
+            tx::{self, MemoryUtxoProvider, Tx, TxOut},
+            Network,
+        };
+
+        const REDEEM_SCRIPT: &str = "5221022626e955ea6ea6d98850c994f9107b036b1334f18ca8830bfff1295d21cfdb702103b287eaf122eea69030a0e9feed096bed8045c8b98bec453e1ffac7fbdbd4bb7152ae";
+
+        /// A 2 of 2 P2SH spend.
+        const TX: &str = "0100000001868278ed6ddfb6c1ed3ad5f8181eb0c7a385aa0836f01d5e4789e6bd304d87221a000000db00483045022100dc92655fe37036f47756db8102e0d7d5e28b3beb83a8fef4f5dc0559bddfb94e02205a36d4e4e6c7fcd16658c50783e00c341609977aed3ad00937bf4ee942a8993701483045022100da6bee3c93766232079a01639d07fa869598749729ae323eab8eef53577d611b02207bef15429dcadce2121ea07f233115c6f09034c0be68db99980b9a6c5e75402201475221022626e955ea6ea6d98850c994f9107b036b1334f18ca8830bfff1295d21cfdb702103b287eaf122eea69030a0e9feed096bed8045c8b98bec453e1ffac7fbdbd4bb7152aeffffffff04d3b11400000000001976a914904a49878c0adfc3aa05de7afad2cc15f483a56a88ac7f400900000000001976a914418327e3f3dda4cf5b9089325a4b95abdfa0334088ac722c0c00000000001976a914ba35042cfe9fc66fd35ac2224eebdafd1028ad2788acdc4ace020000000017a91474d691da1574e6b3c192ecfb52cc8984ee7b6c568700000000";
+
+        fn redeem_script() -> Script {
+            Script::parse_raw(&hex::decode(REDEEM_SCRIPT).unwrap()).unwrap()
+        }
+
+        fn utxos(tx: &Tx) -> MemoryUtxoProvider {
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert(
+                tx.tx_ins[0].prev_tx,
+                tx.tx_ins[0].prev_index,
+                TxOut::new(100_000_000, redeem_script().to_p2sh()),
+            );
+            utxos
+        }
+
+        #[test]
+        fn multisig() {
+            let public_keys = [
+                "022626e955ea6ea6d98850c994f9107b036b1334f18ca8830bfff1295d21cfdb70",
+                "03b287eaf122eea69030a0e9feed096bed8045c8b98bec453e1ffac7fbdbd4bb71",
+            ]
+            .map(|sec| PublicKey::parse_sec(&hex::decode(sec).unwrap()).unwrap());
+
+            assert_eq!(Script::multisig(2, &public_keys).unwrap(), redeem_script());
+            assert!(Script::multisig(0, &public_keys).is_err());
+            assert!(Script::multisig(3, &public_keys).is_err());
+        }
+
+        #[test]
+        fn p2sh_address() {
+            assert_eq!(
+                hex::encode(&redeem_script().to_p2sh().p2sh_hash().unwrap()),
+                "74d691da1574e6b3c192ecfb52cc8984ee7b6c56"
+            );
+            assert_eq!(
+                redeem_script().p2sh_address(Network::Mainnet),
+                "3CLoMMyuoDQTPRD3XYZtCvgvkadrAdvdXh"
+            );
+            assert_eq!(
+                redeem_script().p2sh_address(Network::Testnet),
+                "2N3u1R6uwQfuobCqbCgBkpsgBxvr1tZpe7B"
+            );
+            assert_eq!(
+                Script::from_address("3CLoMMyuoDQTPRD3XYZtCvgvkadrAdvdXh").unwrap(),
+                redeem_script().to_p2sh()
+            );
+        }
+
+        #[test]
+        fn sig_hash() {
+            let tx = Tx::parse(&mut hex::decode(TX).unwrap().as_slice()).unwrap();
+
+            assert_eq!(
+                tx.sig_hash(0, &redeem_script(), tx::SIGHASH_ALL),
+                U256::from_be_hex(
+                    "e71bfa115715d6fd33796948126f40a8cdd39f187e4afb03896795189fe1423c"
+                )
+            );
+        }
+
+        #[test]
+        fn verify() {
+            let tx = Tx::parse(&mut hex::decode(TX).unwrap().as_slice()).unwrap();
+            let utxos = utxos(&tx);
+
+            assert!(tx.verify(&utxos).is_ok());
+
+            // Signatures out of order
+            let mut swapped = tx.clone();
+            let mut cmds = tx.tx_ins[0].script_sig.cmds().unwrap();
+            cmds.swap(1, 2);
+            swapped.tx_ins[0].script_sig = Script::new(cmds);
+            assert!(swapped.verify(&utxos).is_err());
+
+            // BIP-16 requires a push only script_sig
+            let mut not_push_only = tx.clone();
+            not_push_only.tx_ins[0].script_sig =
+                Script::new(vec![Command::Op(Opcode::OP_NOP)]) + tx.tx_ins[0].script_sig.clone();
+            assert!(matches!(
+                not_push_only.verify(&utxos),
+                Err(crate::Error::Script {
+                    opcode: None,
+                    error: ScriptError::SigPushOnly
+                })
+            ));
+        }
+    }
+
+    mod chapter13 {
+        use crypto_bigint::U256;
+
+        use super::util::{parse_tx, script};
+        use crate::{
+            hex,
+            private_key::PrivateKey,
+            script::{Command, Opcode, Script, ScriptError},
+            tx::{self, MemoryUtxoProvider, Tx, TxIn, TxOut},
+            tx_builder::TxBuilder,
+            Network,
+        };
+
+        const SEGWIT_TX: &str = "02000000000101595895ea20179de87052b4046dfe6fd515860505d6511a9004cf12a1f93cac7c0100000000ffffffff01deb807000000000017a9140f3444e271620c736808aa7b33e370bd87cb5a078702483045022100fb60dad8df4af2841adc0346638c16d0b8035f5e3f3753b88db122e70c79f9370220756e6633b17fd2710e626347d28d60b0a2d6cbb41de51740644b9fb3ba7751040121028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc00000000";
+
+        /// Spends a P2PKH output and two P2SH-P2WPKH outputs of the `SPENT_*` transactions.
+        const SPENDING: &str = "020000000001031cfbc8f54fbfa4a33a30068841371f80dbfe166211242213188428f437445c91000000006a47304402206fbcec8d2d2e740d824d3d36cc345b37d9f65d665a99f5bd5c9e8d42270a03a8022013959632492332200c2908459547bf8dbf97c65ab1a28dec377d6f1d41d3d63e012103d7279dfb90ce17fe139ba60a7c41ddf605b25e1c07a4ddcb9dfef4e7d6710f48feffffff476222484f5e35b3f0e43f65fc76e21d8be7818dd6a989c160b1e5039b7835fc00000000171600140914414d3c94af70ac7e25407b0689e0baa10c77feffffffa83d954a62568bbc99cc644c62eb7383d7c2a2563041a0aeb891a6a4055895570000000017160014795d04cc2d4f31480d9a3710993fbd80d04301dffeffffff06fef72f000000000017a91476fd7035cd26f1a32a5ab979e056713aac25796887a5000f00000000001976a914b8332d502a529571c6af4be66399cd33379071c588ac3fda0500000000001976a914fc1d692f8de10ae33295f090bea5fe49527d975c88ac522e1b00000000001976a914808406b54d1044c429ac54c0e189b0d8061667e088ac6eb68501000000001976a914dfab6085f3a8fb3e6710206a5a959313c5618f4d88acbba20000000000001976a914eb3026552d7e3f3073457d0bee5d4757de48160d88ac0002483045022100bee24b63212939d33d513e767bc79300051f7a0d433c3fcf1e0e3bf03b9eb1d70220588dc45a9ce3a939103b4459ce47500b64e23ab118dfc03c9caa7d6bfc32b9c601210354fd80328da0f9ae6eef2b3a81f74f9a6f66761fadf96f1d1d22b1fd6845876402483045022100e29c7e3a5efc10da6269e5fc20b6a1cb8beb92130cc52c67e46ef40aaa5cac5f0220644dd1b049727d991aece98a105563416e10a5ac4221abac7d16931842d5c322012103960b87412d6e169f30e12106bdf70122aabb9eb61f455518322a18b920a4dfa887d30700";
+        const SPENT_1: &str = "020000000001040aacd2c49f5f3c0968cfa8caf9d5761436d95385252e3abb4de8f5dcf8a582f20000000017160014bcadb2baea98af0d9a902e53a7e9adff43b191e9feffffff96cd3c93cac3db114aafe753122bd7d1afa5aa4155ae04b3256344ecca69d72001000000171600141d9984579ceb5c67ebfbfb47124f056662fe7adbfeffffffc878dd74d3a44072eae6178bb94b9253177db1a5aaa6d068eb0e4db7631762e20000000017160014df2a48cdc53dae1aba7aa71cb1f9de089d75aac3feffffffe49f99275bc8363f5f593f4eec371c51f62c34ff11cc6d8d778787d340d6896c0100000017160014229b3b297a0587e03375ab4174ef56eeb0968735feffffff03360d0f00000000001976a9149f44b06f6ee92ddbc4686f71afe528c09727a5c788ac24281b00000000001976a9140277b4f68ff20307a2a9f9b4487a38b501eb955888ac227c0000000000001976a9148020cd422f55eef8747a9d418f5441030f7c9c7788ac0247304402204aa3bd9682f9a8e101505f6358aacd1749ecf53a62b8370b97d59243b3d6984f02200384ad449870b0e6e89c92505880411285ecd41cf11e7439b973f13bad97e53901210205b392ffcb83124b1c7ce6dd594688198ef600d34500a7f3552d67947bbe392802473044022033dfd8d190a4ae36b9f60999b217c775b96eb10dee3a1ff50fb6a75325719106022005872e4e36d194e49ced2ebcf8bb9d843d842e7b7e0eb042f4028396088d292f012103c9d7cbf369410b090480de2aa15c6c73d91b9ffa7d88b90724614b70be41e98e0247304402207d952de9e59e4684efed069797e3e2d993e9f98ec8a9ccd599de43005fe3f713022076d190cc93d9513fc061b1ba565afac574e02027c9efbfa1d7b71ab8dbb21e0501210313ad44bc030cc6cb111798c2bf3d2139418d751c1e79ec4e837ce360cc03b97a024730440220029e75edb5e9413eb98d684d62a077b17fa5b7cc19349c1e8cc6c4733b7b7452022048d4b9cae594f03741029ff841e35996ef233701c1ea9aa55c301362ea2e2f68012103590657108a72feb8dc1dec022cf6a230bb23dc7aaa52f4032384853b9f8388baf9d20700";
+        const SPENT_2: &str = "0200000000010166c3d39490dc827a2594c7b17b7d37445e1f4b372179649cd2ce4475e3641bbb0100000017160014e69aa750e9bff1aca1e32e57328b641b611fc817fdffffff01e87c5d010000000017a914f3890da1b99e44cd3d52f7bcea6a1351658ea7be87024830450221009eb97597953dc288de30060ba02d4e91b2bde1af2ecf679c7f5ab5989549aa8002202a98f8c3bd1a5a31c0d72950dd6e2e3870c6c5819a6c3db740e91ebbbc5ef4800121023f3d3b8e74b807e32217dea2c75c8d0bd46b8665b3a2d9b3cb310959de52a09bc9d20700";
+        const SPENT_3: &str = "01000000027a1120a30cef95422638e8dab9dedf720ec614b1b21e451a4957a5969afb869d000000006a47304402200ecc318a829a6cad4aa9db152adbf09b0cd2de36f47b53f5dade3bc7ef086ca702205722cda7404edd6012eedd79b2d6f24c0a0c657df1a442d0a2166614fb164a4701210372f4b97b34e9c408741cd1fc97bcc7ffdda6941213ccfde1cb4075c0f17aab06ffffffffc23b43e5a18e5a66087c0d5e64d58e8e21fcf83ce3f5e4f7ecb902b0e80a7fb6010000006b483045022100f10076a0ea4b4cf8816ed27a1065883efca230933bf2ff81d5db6258691ff75202206b001ef87624e76244377f57f0c84bc5127d0dd3f6e0ef28b276f176badb223a01210309a3a61776afd39de4ed29b622cd399d99ecd942909c36a8696cfd22fc5b5a1affffffff0200127a000000000017a914f895e1dd9b29cb228e9b06a15204e3b57feaf7cc8769311d09000000001976a9144d00da12aaa51849d2583ae64525d4a06cd70fde88ac00000000";
+
+        #[test]
+        fn parse() {
+            let tx = parse_tx(SEGWIT_TX);
+
+            assert!(tx.is_segwit());
+            assert_eq!(tx.tx_ins[0].witness.len(), 2);
+            assert_eq!(hex::encode(&tx.serialize()), SEGWIT_TX);
+            assert_eq!(
+                tx.id(),
+                "f5864806e3565c34d1b41e716f72609d00b55ea5eac5b924c9719a842ef42206"
+            );
+            assert_eq!(
+                tx.wtxid(),
+                "80b7d8a82d5d5bf92905b06f2014dd699e03837ca172e3a59d51426ebbe3e7f5"
+            );
+            assert_eq!(tx.weight(), 442);
+            assert_eq!(tx.vsize(), 111);
+
+            // Without witnesses both hashes match
+            let mut stripped = tx.clone();
+            stripped.tx_ins[0].witness.clear();
+            assert_eq!(stripped.id(), tx.id());
+            assert_eq!(stripped.wtxid(), tx.id());
+
+            // A segwit marker must come with witnesses
+            let mut bytes = hex::decode(SEGWIT_TX).unwrap();
+            bytes[5] = 0x02;
+            assert!(Tx::parse(&mut bytes.as_slice()).is_err());
+        }
+
+        #[test]
+        fn sig_hash_bip143() {
+            // BIP-143 native P2WPKH and P2SH-P2WPKH examples
+            let tx = parse_tx("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000");
+            assert_eq!(
+                tx.sig_hash_bip143(
+                    1,
+                    &script("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac"),
+                    600_000_000,
+                    tx::SIGHASH_ALL
+                )
+                .unwrap(),
+                U256::from_be_hex(
+                    "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+                )
+            );
+            assert!(tx
+                .sig_hash_bip143(
+                    2,
+                    &script("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac"),
+                    600_000_000,
+                    tx::SIGHASH_ALL
+                )
+                .is_err());
+
+            let tx = parse_tx("0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000");
+            assert_eq!(
+                tx.sig_hash_bip143(
+                    0,
+                    &script("76a91479091972186c449eb1ded22b78e40d009bdf008988ac"),
+                    1_000_000_000,
+                    tx::SIGHASH_ALL
+                )
+                .unwrap(),
+                U256::from_be_hex(
+                    "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+                )
+            );
+
+            // BIP-143 P2SH-P2WSH example, with every sighash type
+            let tx = parse_tx("010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000");
+            let witness_script = script("56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae");
+            let cases = [
+                (
+                    tx::SIGHASH_ALL,
+                    "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+                ),
+                (
+                    tx::SIGHASH_NONE,
+                    "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+                ),
+                (
+                    tx::SIGHASH_SINGLE,
+                    "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+                ),
+                (
+                    tx::SIGHASH_ALL | tx::SIGHASH_ANYONECANPAY,
+                    "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+                ),
+                (
+                    tx::SIGHASH_NONE | tx::SIGHASH_ANYONECANPAY,
+                    "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+                ),
+                (
+                    tx::SIGHASH_SINGLE | tx::SIGHASH_ANYONECANPAY,
+                    "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+                ),
+            ];
+
+            for (sighash_type, expected) in cases {
+                assert_eq!(
+                    tx.sig_hash_bip143(0, &witness_script, 987_654_321, sighash_type)
+                        .unwrap(),
+                    U256::from_be_hex(expected)
+                );
+            }
+        }
+
+        #[test]
+        fn verify_p2sh_p2wpkh() {
+            let tx = parse_tx(SPENDING);
+
+            let mut utxos = MemoryUtxoProvider::new();
+            for spent in [SPENT_1, SPENT_2, SPENT_3] {
+                utxos.insert_tx(&parse_tx(spent));
+            }
+
+            assert!(tx.verify(&utxos).is_ok());
+
+            let mut corrupted = tx.clone();
+            corrupted.tx_ins[1].witness[0][10] = 42;
+            assert!(corrupted.verify(&utxos).is_err());
+
+            let mut stripped = tx.clone();
+            stripped.tx_ins[1].witness.clear();
+            assert!(stripped.verify(&utxos).is_err());
+
+            // A witness on the legacy input
+            let mut unexpected = tx.clone();
+            unexpected.tx_ins[0].witness = vec![vec![1]];
+            assert!(matches!(
+                unexpected.verify_input(0, &utxos),
+                Err(crate::Error::Script {
+                    opcode: None,
+                    error: ScriptError::WitnessUnexpected
+                })
+            ));
+        }
+
+        #[test]
+        fn verify_p2wsh() {
+            let private_key = PrivateKey::new(U256::from_u64(0xc0ffee)).unwrap();
+            let witness_script = Script::new(vec![
+                Command::Data(private_key.point().sec(true)),
+                Command::Op(Opcode::OP_CHECKSIG),
+            ]);
+            let script_pubkey = witness_script.to_p2wsh();
+
+            let prev_tx = [0x11; 32];
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert(prev_tx, 0, TxOut::new(50_000, script_pubkey.clone()));
+
+            let mut tx = Tx {
+                version: 2,
+                tx_ins: vec![TxIn::new(prev_tx, 0)],
+                tx_outs: vec![TxOut::new(40_000, script_pubkey)],
+                locktime: 0,
+            };
+
+            let z = tx
+                .sig_hash_bip143(0, &witness_script, 50_000, tx::SIGHASH_ALL)
+                .unwrap();
+            let mut sig = private_key.sign(z).unwrap().der();
+            sig.push(tx::SIGHASH_ALL as u8);
+            tx.tx_ins[0].witness = vec![sig.clone(), witness_script.raw_serialize()];
+
+            assert!(tx.verify(&utxos).is_ok());
+
+            // Native witness programs take an empty script_sig
+            let mut malleated = tx.clone();
+            malleated.tx_ins[0].script_sig = Script::new(vec![Command::Op(Opcode::OP_1)]);
+            assert!(matches!(
+                malleated.verify(&utxos),
+                Err(crate::Error::Script {
+                    opcode: None,
+                    error: ScriptError::WitnessMalleated
+                })
+            ));
+
+            // The witness script must hash to the program
+            let mut wrong_script = tx.clone();
+            wrong_script.tx_ins[0].witness = vec![sig, vec![Opcode::OP_1.to_byte()]];
+            assert!(matches!(
+                wrong_script.verify(&utxos),
+                Err(crate::Error::Script {
+                    opcode: None,
+                    error: ScriptError::WitnessProgramMismatch
+                })
+            ));
+        }
+
+        #[test]
+        fn tx_builder() {
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+            let hash = private_key.point().hash160(true);
+            let p2wpkh = Script::p2wpkh(&hash);
+            let p2sh_p2wpkh = p2wpkh.to_p2sh();
+
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert([0x01; 32], 0, TxOut::new(30_000, p2wpkh.clone()));
+            utxos.insert([0x02; 32], 3, TxOut::new(40_000, p2sh_p2wpkh.clone()));
+
+            let tx = TxBuilder::new(1, &p2sh_p2wpkh.address(Network::Testnet).unwrap())
+                .add_utxo([0x01; 32], 0, TxOut::new(30_000, p2wpkh), private_key)
+                .add_utxo([0x02; 32], 3, TxOut::new(40_000, p2sh_p2wpkh), private_key)
+                .add_output("mwJn1YPMq7y5F8J3LkC5Hxg9PHyZ5K4cFv", 50_000)
+                .build()
+                .unwrap();
+
+            assert!(tx.is_segwit());
+            assert_eq!(tx.tx_outs.len(), 2);
+            assert!(tx.verify(&utxos).is_ok());
+
+            assert_eq!(tx.fee(&utxos).unwrap() as usize, tx.vsize());
+        }
+
+        #[test]
+        fn tx_builder_vector() {
+            // Signed outside this crate with OpenSSL's RFC 6979 nonces, which give low-R
+            // signatures here
+            let expected = "020000000001030101010101010101010101010101010101010101010101010101010101010101000000006a473044022009873349aa882b3e026fd96c34a03a174650f90e844369f2c6e2d17677b1348c02202b4985d050318e46d27faf1c1b6f08c4798d1caa79ec47a746faa252b2214a9c012103935581e52c354cd2f484fe8ed83af7a3097005b2f9c60bff71d35bd795f54b67ffffffff02020202020202020202020202020202020202020202020202020202020202020100000000ffffffff03030303030303030303030303030303030303030303030303030303030303030200000017160014d52ad7ca9b3d096a38e752c2018e6fbc40cdf26fffffffff02d07e0100000000001976a914ad346f8eb57dee9a37981716e498120ae80e44f788acf452000000000000160014d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f000247304402202a503b857b00e47178bdd8c002f8903a95b9be7fb94a84890b8311b3410ddb8b02204a458abaf9e2daac227377d4e1e4384ebde300348751a6c54f0479337edd68e8012103935581e52c354cd2f484fe8ed83af7a3097005b2f9c60bff71d35bd795f54b6702473044022012ae4f9de0f078fe43de5775798517d7afa1f1d428f8ef1ecffc04c9fdcd1ad502205e1be16113ae6ba9e55d7c710b9eda1c6a0d172883e59a81fe8eed817ef826f0012103935581e52c354cd2f484fe8ed83af7a3097005b2f9c60bff71d35bd795f54b6700000000";
+
+            let private_key = PrivateKey::new(U256::from_u64(8675309)).unwrap();
+            let hash = private_key.point().hash160(true);
+            let p2wpkh = Script::p2wpkh(&hash);
+
+            let tx = TxBuilder::new(2, &p2wpkh.address(Network::Testnet).unwrap())
+                .add_utxo(
+                    [0x01; 32],
+                    0,
+                    TxOut::new(30_000, Script::p2pkh(&hash)),
+                    private_key,
+                )
+                .add_utxo(
+                    [0x02; 32],
+                    1,
+                    TxOut::new(40_000, p2wpkh.clone()),
+                    private_key,
+                )
+                .add_utxo(
+                    [0x03; 32],
+                    2,
+                    TxOut::new(50_000, p2wpkh.to_p2sh()),
+                    private_key,
+                )
+                .add_output("mwJn1YPMq7y5F8J3LkC5Hxg9PHyZ5K4cFv", 98_000)
+                .build()
+                .unwrap();
+
+            assert_eq!(hex::encode(&tx.serialize()), expected);
+            assert_eq!(tx.vsize(), 382);
+            assert_eq!(tx.tx_outs[1].amount, 120_000 - 98_000 - 2 * 382);
+        }
+    }
+
+    mod bech32 {
+        use crate::{
+            bech32::{self, Variant},
+            hex,
+            script::Script,
+            Network,
+        };
+
+        #[test]
+        fn decode_valid() {
+            let bech32 = [
+                "A12UEL5L",
+                "a12uel5l",
+                "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+                "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+                "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+                "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+                "?1ezyfcl",
+            ];
+            let bech32m = [
+                "A1LQFN3A",
+                "a1lqfn3a",
+                "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+                "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+                "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+                "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+                "?1v759aa",
+            ];
+
+            for (strings, variant) in [(bech32, Variant::Bech32), (bech32m, Variant::Bech32m)] {
+                for s in strings {
+                    let (hrp, data, decoded_variant) = bech32::decode(s).unwrap();
+                    assert_eq!(decoded_variant, variant);
+                    assert_eq!(
+                        bech32::encode(&hrp, &data, variant).unwrap(),
+                        s.to_lowercase()
+                    );
+
+                    // Flipping a character must break the checksum
+                    let mut flipped = s.to_lowercase().into_bytes();
+                    let last = flipped.len() - 1;
+                    flipped[last] = if flipped[last] == b'q' { b'p' } else { b'q' };
+                    assert!(bech32::decode(std::str::from_utf8(&flipped).unwrap()).is_err());
+                }
+            }
+        }
+
+        #[test]
+        fn decode_invalid() {
+            let cases = [
+                // Characters outside the printable range
+                "\x201nwldj5",
+                "\x7f1axkwrx",
+                "\u{80}1eym55h",
+                // Too long
+                "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+                // No separator, or an empty human-readable part
+                "pzry9x0s0muk",
+                "1pzry9x0s0muk",
+                // Characters outside the charset
+                "x1b4n0q5v",
+                "de1lg7wt\u{ff}",
+                // Checksum too short
+                "li1dgmt3",
+                // Checksum computed over an uppercase human-readable part
+                "A1G7SGD8",
+                "10a06t8",
+                "1qzzfhee",
+                // Mixed case
+                "A12uEL5L",
+                // Valid as bech32m, but the same data with a bech32 checksum is invalid
+                "M1VUXWEZ",
+                "16plkw9",
+                "1p2gdwpf",
+            ];
+
+            for s in cases {
+                assert!(bech32::decode(s).is_err(), "{s}");
+            }
+
+            assert!(matches!(
+                bech32::decode("a12uel5m"),
+                Err(crate::Error::ChecksumMismatch(_))
+            ));
+        }
+
+        #[test]
+        fn convert_bits() {
+            let bytes = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+            let five_bits = bech32::convert_bits(&bytes, 8, 5, true).unwrap();
+            assert_eq!(five_bits.len(), 32);
+            assert_eq!(
+                bech32::convert_bits(&five_bits, 5, 8, false).unwrap(),
+                bytes
+            );
+
+            // Non-zero padding
+            assert!(bech32::convert_bits(&[0x1f], 5, 8, false).is_err());
+            // A value that does not fit
+            assert!(bech32::convert_bits(&[0x20], 5, 8, true).is_err());
+        }
+
+        #[test]
+        fn segwit_addresses() {
+            let cases = [
+                (
+                    "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+                    "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                ),
+                (
+                    "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                    "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                ),
+                (
+                    "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                    "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+                ),
+                ("BC1SW50QGDZ25J", "6002751e"),
+                (
+                    "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                    "5210751e76e8199196d454941c45d1b3a323",
+                ),
+                (
+                    "tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy",
+                    "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+                ),
+                (
+                    "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+                    "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+                ),
+                (
+                    "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                    "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                ),
+            ];
+
+            for (address, script_pubkey) in cases {
+                let script = Script::from_address(address).unwrap();
+                assert_eq!(hex::encode(&script.raw_serialize()), script_pubkey);
+
+                let network = if address.to_lowercase().starts_with("bc") {
+                    Network::Mainnet
+                } else {
+                    Network::Testnet
+                };
+                assert_eq!(script.address(network).unwrap(), address.to_lowercase());
+            }
+        }
+
+        #[test]
+        fn invalid_segwit_addresses() {
+            let cases = [
+                // Unknown human-readable part
+                "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+                // Wrong checksum variant for the witness version
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+                "tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+                "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+                "tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+                // Invalid character in the checksum
+                "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+                // Witness version above 16
+                "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+                // Invalid program lengths
+                "bc1pw5dgrnzv",
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+                "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+                // Mixed case
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+                // Zero padding of more than 4 bits
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+                // Non-zero padding
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+                // Empty data section
+                "bc1gmk9yu",
+            ];
+
+            for address in cases {
+                let hrp = if address.to_lowercase().starts_with("tb") {
+                    "tb"
+                } else {
+                    "bc"
+                };
+                assert!(bech32::decode_segwit(hrp, address).is_err(), "{address}");
+            }
+
+            assert!(bech32::encode_segwit("bc", 0, &[0; 21]).is_err());
+            assert!(bech32::encode_segwit("bc", 17, &[0; 32]).is_err());
+            assert!(bech32::encode_segwit("bc", 1, &[0; 41]).is_err());
+        }
+
+        #[test]
+        fn from_witness_program() {
+            assert_eq!(
+                hex::encode(
+                    &Script::from_witness_program(16, &[0x75, 0x1e])
+                        .unwrap()
+                        .raw_serialize()
+                ),
+                "6002751e"
+            );
+            assert!(Script::from_witness_program(1, &[0; 40]).is_ok());
+
+            assert!(Script::from_witness_program(17, &[0; 32]).is_err());
+            assert!(Script::from_witness_program(1, &[0; 1]).is_err());
+            assert!(Script::from_witness_program(1, &[0; 41]).is_err());
+            assert!(Script::from_witness_program(0, &[0; 21]).is_err());
+        }
+    }
+
+    mod schnorr {
+        use crypto_bigint::U256;
+
+        use crate::{
+            hex,
+            point::Point,
+            private_key::PrivateKey,
+            schnorr::{self, SchnorrSignature, XOnlyPublicKey},
+            secp256k1::{G, N},
+        };
+
+        /// The official BIP-340 test vectors, test-vectors.csv.
+        const VECTORS: &str = r#"index,secret key,public key,aux_rand,message,signature,verification result,comment
+0,0000000000000000000000000000000000000000000000000000000000000003,F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9,0000000000000000000000000000000000000000000000000000000000000000,0000000000000000000000000000000000000000000000000000000000000000,E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0,TRUE,
+1,B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,0000000000000000000000000000000000000000000000000000000000000001,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A,TRUE,
+2,C90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B14E5C9,DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8,C87AA53824B4D7AE2EB035A2B5BBBCCC080E76CDC6D1692C4B0B62D798E6D906,7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C,5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7,TRUE,
+3,0B432B2677937381AEF05BB02A66ECD012773062CF3FA2549E44F58ED2401710,25D1DFF95105F5253C4022F628A996AD3A0D95FBF21D468A1B33F8C160D8F517,FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF,FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF,7EB0509757E246F19449885651611CB965ECC1A187DD51B64FDA1EDC9637D5EC97582B9CB13DB3933705B32BA982AF5AF25FD78881EBB32771FC5922EFC66EA3,TRUE,test fails if msg is reduced modulo p or n
+4,,D69C3509BB99E412E68B0FE8544E72837DFA30746D8BE2AA65975F29D22DC7B9,,4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703,00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C6376AFB1548AF603B3EB45C9F8207DEE1060CB71C04E80F593060B07D28308D7F4,TRUE,
+5,,EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B,FALSE,public key not on the curve
+6,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2,FALSE,has_even_y(R) is false
+7,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,1FA62E331EDBC21C394792D2AB1100A7B432B013DF3F6FF4F99FCB33E0E1515F28890B3EDB6E7189B630448B515CE4F8622A954CFE545735AAEA5134FCCDB2BD,FALSE,negated message
+8,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769961764B3AA9B2FFCB6EF947B6887A226E8D7C93E00C5ED0C1834FF0D0C2E6DA6,FALSE,negated s value
+9,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,0000000000000000000000000000000000000000000000000000000000000000123DDA8328AF9C23A94C1FEECFD123BA4FB73476F0D594DCB65C6425BD186051,FALSE,sG - eP is infinite. Test fails in single verification if has_even_y(inf) is defined as true and x(inf) as 0
+10,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,00000000000000000000000000000000000000000000000000000000000000017615FBAF5AE28864013C099742DEADB4DBA87F11AC6754F93780D5A1837CF197,FALSE,sG - eP is infinite. Test fails in single verification if has_even_y(inf) is defined as true and x(inf) as 1
+11,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B,FALSE,sig[0:32] is not an X coordinate on the curve
+12,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B,FALSE,sig[0:32] is equal to field size
+13,,DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141,FALSE,sig[32:64] is equal to curve order
+14,,FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30,,243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89,6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B,FALSE,public key is not a valid X coordinate because it exceeds the field size
+15,0340034003400340034003400340034003400340034003400340034003400340,778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117,0000000000000000000000000000000000000000000000000000000000000000,,71535DB165ECD9FBBC046E5FFAEA61186BB6AD436732FCCC25291A55895464CF6069CE26BF03466228F19A3A62DB8A649F2D560FAC652827D1AF0574E427AB63,TRUE,message of size 0 (added 2022-12)
+16,0340034003400340034003400340034003400340034003400340034003400340,778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117,0000000000000000000000000000000000000000000000000000000000000000,11,08A20A0AFEF64124649232E0693C583AB1B9934AE63B4C3511F3AE1134C6A303EA3173BFEA6683BD101FA5AA5DBC1996FE7CACFC5A577D33EC14564CEC2BACBF,TRUE,message of size 1 (added 2022-12)
+17,0340034003400340034003400340034003400340034003400340034003400340,778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117,0000000000000000000000000000000000000000000000000000000000000000,0102030405060708090A0B0C0D0E0F1011,5130F39A4059B43BC7CAC09A19ECE52B5D8699D1A71E3C52DA9AFDB6B50AC370C4A482B77BF960F8681540E25B6771ECE1E5A37FD80E5A51897C5566A97EA5A5,TRUE,message of size 17 (added 2022-12)
+18,0340034003400340034003400340034003400340034003400340034003400340,778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117,0000000000000000000000000000000000000000000000000000000000000000,99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999,403B12B0D8555A344175EA7EC746566303321E5DBFA8BE6F091635163ECA79A8585ED3E3170807E7C03B720FC54C7B23897FCBA0E9D0B4A06894CFD249F22367,TRUE,message of size 100 (added 2022-12)
+"#;
+
+        #[test]
+        fn bip340_vectors() {
+            for line in VECTORS.lines().skip(1) {
+                let fields: Vec<&str> = line.splitn(8, ',').collect();
+                let [index, secret, public_key, aux_rand, msg, signature, result, _] = fields[..]
+                else {
+                    panic!("malformed vector {line}");
+                };
+                let msg = hex::decode(msg).unwrap();
+                let signature = hex::decode(signature).unwrap();
+
+                if !secret.is_empty() {
+                    let private_key = PrivateKey::new(U256::from_be_hex(secret)).unwrap();
+                    let aux_rand: [u8; 32] = hex::decode(aux_rand).unwrap().try_into().unwrap();
+
+                    assert_eq!(
+                        private_key.x_only_public_key().serialize().to_vec(),
+                        hex::decode(public_key).unwrap(),
+                        "vector {index}"
+                    );
+                    assert_eq!(
+                        private_key
+                            .sign_schnorr(&msg, &aux_rand)
+                            .unwrap()
+                            .serialize()
+                            .to_vec(),
+                        signature,
+                        "vector {index}"
+                    );
+                }
+
+                let valid = match (
+                    XOnlyPublicKey::parse(&hex::decode(public_key).unwrap()),
+                    SchnorrSignature::parse(&signature),
+                ) {
+                    (Ok(public_key), Ok(signature)) => public_key.verify(&msg, &signature),
+                    _ => false,
+                };
+                assert_eq!(valid, result == "TRUE", "vector {index}");
+            }
+        }
+
+        #[test]
+        fn batch_verify() {
+            let vectors: Vec<(XOnlyPublicKey, Vec<u8>, SchnorrSignature)> = VECTORS
+                .lines()
+                .skip(1)
+                .map(|line| line.split(',').collect::<Vec<_>>())
+                .filter(|fields| fields[6] == "TRUE")
+                .map(|fields| {
+                    (
+                        XOnlyPublicKey::parse(&hex::decode(fields[2]).unwrap()).unwrap(),
+                        hex::decode(fields[4]).unwrap(),
+                        SchnorrSignature::parse(&hex::decode(fields[5]).unwrap()).unwrap(),
+                    )
+                })
+                .collect();
+            let batch: Vec<(XOnlyPublicKey, &[u8], SchnorrSignature)> = vectors
+                .iter()
+                .map(|(public_key, msg, sig)| (*public_key, &msg[..], *sig))
+                .collect();
+
+            assert_eq!(batch.len(), 9);
+            assert_eq!(schnorr::batch_verify(&batch), Ok(()));
+            assert_eq!(schnorr::batch_verify(&[]), Ok(()));
+
+            // Invalid signatures in a smaller batch, to keep the one by one fallback short
+            let mut batch = batch[..4].to_vec();
+
+            // A signature of another message
+            let mut wrong_message = batch.clone();
+            wrong_message[3].1 = b"another message";
+            assert_eq!(schnorr::batch_verify(&wrong_message), Err(3));
+
+            // Two signatures swapped between keys
+            let (sig_1, sig_2) = (batch[1].2, batch[2].2);
+            batch[1].2 = sig_2;
+            batch[2].2 = sig_1;
+            assert_eq!(schnorr::batch_verify(&batch), Err(1));
+            batch[1].2 = sig_1;
+            batch[2].2 = sig_2;
+
+            // r is not an x coordinate on the curve
+            batch[2].2.r = U256::from_be_hex(
+                "4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D",
+            );
+            assert_eq!(schnorr::batch_verify(&batch), Err(2));
+
+            assert_eq!(
+                Point::multi_scalar_mul(&[(G, U256::from_u64(2)), (G, U256::from_u64(3))]),
+                G.scalar_mul(5)
+            );
+        }
+
+        #[test]
+        fn x_only() {
+            // G has an even y, so -G has an odd one and shares its x-only key
+            let point = G.scalar_mul_u256(N - U256::ONE);
+            assert!(G.has_even_y() && !point.has_even_y());
+
+            let x_only = XOnlyPublicKey::from_point(&point).unwrap();
+            assert_eq!(x_only.point(), G);
+            assert_eq!(x_only, XOnlyPublicKey::from_point(&G).unwrap());
+            assert_eq!(XOnlyPublicKey::parse(&x_only.serialize()).unwrap(), x_only);
+
+            assert!(XOnlyPublicKey::from_point(&Point::Infinity).is_err());
+            assert!(XOnlyPublicKey::parse(&point.sec(true)).is_err());
+            assert!(SchnorrSignature::parse(&[0; 63]).is_err());
+        }
+    }
+
+    mod taproot {
+        use crypto_bigint::U256;
+
+        use super::util::{parse_tx, script};
+        use crate::{
+            hex,
+            private_key::PrivateKey,
+            schnorr::XOnlyPublicKey,
+            script::{Command, Opcode, Script, ScriptError},
+            taproot::{self, ControlBlock, TapTree},
+            tx::{self, MemoryUtxoProvider, Tx, TxIn, TxOut},
+            Network,
+        };
+
+        /// From the BIP-341 test vectors, bip341_tests.json.
+        const UNSIGNED_TX: &str = "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d";
+        const SIGNED_TX: &str = "020000000001097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a41842000000006b4830450221008f3b8f8f0537c420654d2283673a761b7ee2ea3c130753103e08ce79201cf32a022079e7ab904a1980ef1c5890b648c8783f4d10103dd62f740d13daa79e298d50c201210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0141ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c030141052aedffc554b41f52b521071793a6b88d6dbca9dba94cf34c83696de0c1ec35ca9c5ed4ab28059bd606a4f3a657eec0bb96661d42921b5f50a95ad33675b54f83000141ff45f742a876139946a149ab4d9185574b98dc919d2eb6754f8abaa59d18b025637a3aa043b91817739554f4ed2026cf8022dbd83e351ce1fabc272841d2510a010140b4010dd48a617db09926f729e79c33ae0b4e94b79f04a1ae93ede6315eb3669de185a17d2b0ac9ee09fd4c64b678a0b61a0a86fa888a273c8511be83bfd6810f0247304402202b795e4de72646d76eab3f0ab27dfa30b810e856ff3a46c9a702df53bb0d8cc302203ccc4d822edab5f35caddb10af1be93583526ccfbade4b4ead350781e2f8adcd012102f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f90141a3785919a2ce3c4ce26f298c3d51619bc474ae24014bcdd31328cd8cfbab2eff3395fa0a16fe5f486d12f22a9cedded5ae74feb4bbe5351346508c5405bcfee0020141ea0c6ba90763c2d3a296ad82ba45881abb4f426b3f87af162dd24d5109edc1cdd11915095ba47c3a9963dc1e6c432939872bc49212fe34c632cd3ab9fed429c4820141bbc9584a11074e83bc8c6759ec55401f0ae7b03ef290c3139814f545b58a9f8127258000874f44bc46db7646322107d4d86aec8e73b8719a61fff761d75b5dd9810065cd1d";
+        const UTXOS_SPENT: [(&str, u64); 9] = [
+            (
+                "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+                420000000,
+            ),
+            (
+                "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                462000000,
+            ),
+            (
+                "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac",
+                294000000,
+            ),
+            (
+                "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                504000000,
+            ),
+            (
+                "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+                630000000,
+            ),
+            ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378000000),
+            (
+                "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                672000000,
+            ),
+            (
+                "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+                546000000,
+            ),
+            (
+                "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                588000000,
+            ),
+        ];
+
+        /// The script of the second output, which does not parse: it ends with a push of 75
+        /// bytes with only 26 left.
+        const INVALID_SCRIPT: &str =
+            "20ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b";
+
+        struct ScriptPubKeyVector {
+            internal_key: &'static str,
+            tree: Option<TapTree>,
+            leaves: &'static [(u8, &'static str)],
+            leaf_hashes: &'static [&'static str],
+            merkle_root: Option<&'static str>,
+            tweak: &'static str,
+            output_key: &'static str,
+            script_pubkey: &'static str,
+            address: &'static str,
+            control_blocks: &'static [&'static str],
+        }
+
+        struct KeyPathVector {
+            input_index: usize,
+            private_key: &'static str,
+            merkle_root: Option<&'static str>,
+            sighash_type: u32,
+            tweaked_private_key: &'static str,
+            sig_hash: &'static str,
+            witness: &'static str,
+        }
+
+        fn leaf(version: u8, script_hex: &str) -> TapTree {
+            TapTree::Leaf {
+                version,
+                script: script(script_hex),
+            }
+        }
+
+        fn bytes32(hex: &str) -> [u8; 32] {
+            hex::decode(hex).unwrap().try_into().unwrap()
+        }
+
+        #[test]
+        fn script_pubkey() {
+            let vectors = [
+            ScriptPubKeyVector {
+                internal_key: "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d",
+                tree: None,
+                leaves: &[],
+                leaf_hashes: &[],
+                merkle_root: None,
+                tweak: "b86e7be8f39bab32a6f2c0443abbc210f0edac0e2c53d501b36b64437d9c6c70",
+                output_key: "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+                script_pubkey: "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+                address: "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5",
+                control_blocks: &[],
+            },
+            ScriptPubKeyVector {
+                internal_key: "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+                tree: Some(leaf(192, "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")),
+                leaves: &[(192, "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")],
+                leaf_hashes: &["5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"],
+                merkle_root: Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+                tweak: "cbd8679ba636c1110ea247542cfbd964131a6be84f873f7f3b62a777528ed001",
+                output_key: "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                script_pubkey: "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                address: "bc1pz37fc4cn9ah8anwm4xqqhvxygjf9rjf2resrw8h8w4tmvcs0863sa2e586",
+                control_blocks: &["c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"],
+            },
+            ScriptPubKeyVector {
+                internal_key: "93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820",
+                tree: Some(leaf(192, "20b617298552a72ade070667e86ca63b8f5789a9fe8731ef91202a91c9f3459007ac")),
+                leaves: &[(192, "20b617298552a72ade070667e86ca63b8f5789a9fe8731ef91202a91c9f3459007ac")],
+                leaf_hashes: &["c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"],
+                merkle_root: Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+                tweak: "6af9e28dbf9d6aaf027696e2598a5b3d056f5fd2355a7fd5a37a0e5008132d30",
+                output_key: "e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                script_pubkey: "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                address: "bc1punvppl2stp38f7kwv2u2spltjuvuaayuqsthe34hd2dyy5w4g58qqfuag5",
+                control_blocks: &["c093478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820"],
+            },
+            ScriptPubKeyVector {
+                internal_key: "ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592",
+                tree: Some(TapTree::branch(leaf(192, "20387671353e273264c495656e27e39ba899ea8fee3bb69fb2a680e22093447d48ac"), leaf(250, "06424950333431"))),
+                leaves: &[(192, "20387671353e273264c495656e27e39ba899ea8fee3bb69fb2a680e22093447d48ac"), (250, "06424950333431")],
+                leaf_hashes: &["8ad69ec7cf41c2a4001fd1f738bf1e505ce2277acdcaa63fe4765192497f47a7", "f224a923cd0021ab202ab139cc56802ddb92dcfc172b9212261a539df79a112a"],
+                merkle_root: Some("6c2dc106ab816b73f9d07e3cd1ef2c8c1256f519748e0813e4edd2405d277bef"),
+                tweak: "9e0517edc8259bb3359255400b23ca9507f2a91cd1e4250ba068b4eafceba4a9",
+                output_key: "712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+                script_pubkey: "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+                address: "bc1pwyjywgrd0ffr3tx8laflh6228dj98xkjj8rum0zfpd6h0e930h6saqxrrm",
+                control_blocks: &["c0ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592f224a923cd0021ab202ab139cc56802ddb92dcfc172b9212261a539df79a112a", "faee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf37865928ad69ec7cf41c2a4001fd1f738bf1e505ce2277acdcaa63fe4765192497f47a7"],
+            },
+            ScriptPubKeyVector {
+                internal_key: "f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8",
+                tree: Some(TapTree::branch(leaf(192, "2044b178d64c32c4a05cc4f4d1407268f764c940d20ce97abfd44db5c3592b72fdac"), leaf(192, "07546170726f6f74"))),
+                leaves: &[(192, "2044b178d64c32c4a05cc4f4d1407268f764c940d20ce97abfd44db5c3592b72fdac"), (192, "07546170726f6f74")],
+                leaf_hashes: &["64512fecdb5afa04f98839b50e6f0cb7b1e539bf6f205f67934083cdcc3c8d89", "2cb2b90daa543b544161530c925f285b06196940d6085ca9474d41dc3822c5cb"],
+                merkle_root: Some("ab179431c28d3b68fb798957faf5497d69c883c6fb1e1cd9f81483d87bac90cc"),
+                tweak: "639f0281b7ac49e742cd25b7f188657626da1ad169209078e2761cefd91fd65e",
+                output_key: "77e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                script_pubkey: "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                address: "bc1pwl3s54fzmk0cjnpl3w9af39je7pv5ldg504x5guk2hpecpg2kgsqaqstjq",
+                control_blocks: &["c1f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd82cb2b90daa543b544161530c925f285b06196940d6085ca9474d41dc3822c5cb", "c1f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd864512fecdb5afa04f98839b50e6f0cb7b1e539bf6f205f67934083cdcc3c8d89"],
+            },
+            ScriptPubKeyVector {
+                internal_key: "e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6f",
+                tree: Some(TapTree::branch(leaf(192, "2072ea6adcf1d371dea8fba1035a09f3d24ed5a059799bae114084130ee5898e69ac"), TapTree::branch(leaf(192, "202352d137f2f3ab38d1eaa976758873377fa5ebb817372c71e2c542313d4abda8ac"), leaf(192, "207337c0dd4253cb86f2c43a2351aadd82cccb12a172cd120452b9bb8324f2186aac")))),
+                leaves: &[(192, "2072ea6adcf1d371dea8fba1035a09f3d24ed5a059799bae114084130ee5898e69ac"), (192, "202352d137f2f3ab38d1eaa976758873377fa5ebb817372c71e2c542313d4abda8ac"), (192, "207337c0dd4253cb86f2c43a2351aadd82cccb12a172cd120452b9bb8324f2186aac")],
+                leaf_hashes: &["2645a02e0aac1fe69d69755733a9b7621b694bb5b5cde2bbfc94066ed62b9817", "ba982a91d4fc552163cb1c0da03676102d5b7a014304c01f0c77b2b8e888de1c", "9e31407bffa15fefbf5090b149d53959ecdf3f62b1246780238c24501d5ceaf6"],
+                merkle_root: Some("ccbd66c6f7e8fdab47b3a486f59d28262be857f30d4773f2d5ea47f7761ce0e2"),
+                tweak: "b57bfa183d28eeb6ad688ddaabb265b4a41fbf68e5fed2c72c74de70d5a786f4",
+                output_key: "91b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+                script_pubkey: "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+                address: "bc1pjxmy65eywgafs5tsunw95ruycpqcqnev6ynxp7jaasylcgtcxczs6n332e",
+                control_blocks: &["c0e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6fffe578e9ea769027e4f5a3de40732f75a88a6353a09d767ddeb66accef85e553", "c0e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6f9e31407bffa15fefbf5090b149d53959ecdf3f62b1246780238c24501d5ceaf62645a02e0aac1fe69d69755733a9b7621b694bb5b5cde2bbfc94066ed62b9817", "c0e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6fba982a91d4fc552163cb1c0da03676102d5b7a014304c01f0c77b2b8e888de1c2645a02e0aac1fe69d69755733a9b7621b694bb5b5cde2bbfc94066ed62b9817"],
+            },
+            ScriptPubKeyVector {
+                internal_key: "55adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d",
+                tree: Some(TapTree::branch(leaf(192, "2071981521ad9fc9036687364118fb6ccd2035b96a423c59c5430e98310a11abe2ac"), TapTree::branch(leaf(192, "20d5094d2dbe9b76e2c245a2b89b6006888952e2faa6a149ae318d69e520617748ac"), leaf(192, "20c440b462ad48c7a77f94cd4532d8f2119dcebbd7c9764557e62726419b08ad4cac")))),
+                leaves: &[(192, "2071981521ad9fc9036687364118fb6ccd2035b96a423c59c5430e98310a11abe2ac"), (192, "20d5094d2dbe9b76e2c245a2b89b6006888952e2faa6a149ae318d69e520617748ac"), (192, "20c440b462ad48c7a77f94cd4532d8f2119dcebbd7c9764557e62726419b08ad4cac")],
+                leaf_hashes: &["f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d", "737ed1fe30bc42b8022d717b44f0d93516617af64a64753b7a06bf16b26cd711", "d7485025fceb78b9ed667db36ed8b8dc7b1f0b307ac167fa516fe4352b9f4ef7"],
+                merkle_root: Some("2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def"),
+                tweak: "6579138e7976dc13b6a92f7bfd5a2fc7684f5ea42419d43368301470f3b74ed9",
+                output_key: "75169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                script_pubkey: "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                address: "bc1pw5tf7sqp4f50zka7629jrr036znzew70zxyvvej3zrpf8jg8hqcssyuewe",
+                control_blocks: &["c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d3cd369a528b326bc9d2133cbd2ac21451acb31681a410434672c8e34fe757e91", "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312dd7485025fceb78b9ed667db36ed8b8dc7b1f0b307ac167fa516fe4352b9f4ef7f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d", "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d737ed1fe30bc42b8022d717b44f0d93516617af64a64753b7a06bf16b26cd711f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d"],
+            },
+            ];
+
+            for vector in vectors {
+                let internal_key =
+                    XOnlyPublicKey::parse(&hex::decode(vector.internal_key).unwrap()).unwrap();
+                let merkle_root = vector.tree.as_ref().map(TapTree::hash);
+                assert_eq!(merkle_root, vector.merkle_root.map(bytes32));
+
+                assert_eq!(
+                    taproot::tap_tweak_hash(&internal_key, merkle_root.as_ref()),
+                    bytes32(vector.tweak)
+                );
+                let (output_key, _) =
+                    taproot::tweak_public_key(&internal_key, merkle_root.as_ref()).unwrap();
+                assert_eq!(output_key.serialize(), bytes32(vector.output_key));
+
+                let script_pubkey = Script::p2tr(&output_key);
+                assert_eq!(
+                    hex::encode(&script_pubkey.raw_serialize()),
+                    vector.script_pubkey
+                );
+                assert_eq!(
+                    script_pubkey.address(Network::Mainnet).unwrap(),
+                    vector.address
+                );
+
+                for (i, (version, script_hex)) in vector.leaves.iter().enumerate() {
+                    let raw_script = hex::decode(script_hex).unwrap();
+                    assert_eq!(
+                        taproot::tap_leaf_hash(*version, &raw_script),
+                        bytes32(vector.leaf_hashes[i])
+                    );
+
+                    let tree = vector.tree.as_ref().unwrap();
+                    let control_block = tree
+                        .control_block(&internal_key, *version, &script(script_hex))
+                        .unwrap();
+                    assert_eq!(
+                        hex::encode(&control_block.serialize()),
+                        vector.control_blocks[i]
+                    );
+                    assert_eq!(
+                        ControlBlock::parse(&control_block.serialize()).unwrap(),
+                        control_block
+                    );
+                    assert!(control_block.verify(&output_key, &raw_script));
+                    assert!(!control_block.verify(&output_key, &raw_script[1..]));
+                }
+            }
+        }
+
+        #[test]
+        fn key_path() {
+            let vectors = [
+            KeyPathVector {
+                input_index: 0,
+                private_key: "6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa",
+                merkle_root: None,
+                sighash_type: 0x03,
+                tweaked_private_key: "2405b971772ad26915c8dcdf10f238753a9b837e5f8e6a86fd7c0cce5b7296d9",
+                sig_hash: "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555",
+                witness: "ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c03",
+            },
+            KeyPathVector {
+                input_index: 1,
+                private_key: "1e4da49f6aaf4e5cd175fe08a32bb5cb4863d963921255f33d3bc31e1343907f",
+                merkle_root: Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+                sighash_type: 0x83,
+                tweaked_private_key: "ea260c3b10e60f6de018455cd0278f2f5b7e454be1999572789e6a9565d26080",
+                sig_hash: "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d",
+                witness: "052aedffc554b41f52b521071793a6b88d6dbca9dba94cf34c83696de0c1ec35ca9c5ed4ab28059bd606a4f3a657eec0bb96661d42921b5f50a95ad33675b54f83",
+            },
+            KeyPathVector {
+                input_index: 3,
+                private_key: "d3c7af07da2d54f7a7735d3d0fc4f0a73164db638b2f2f7c43f711f6d4aa7e64",
+                merkle_root: Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+                sighash_type: 0x01,
+                tweaked_private_key: "97323385e57015b75b0339a549c56a948eb961555973f0951f555ae6039ef00d",
+                sig_hash: "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669",
+                witness: "ff45f742a876139946a149ab4d9185574b98dc919d2eb6754f8abaa59d18b025637a3aa043b91817739554f4ed2026cf8022dbd83e351ce1fabc272841d2510a01",
+            },
+            KeyPathVector {
+                input_index: 4,
+                private_key: "f36bb07a11e469ce941d16b63b11b9b9120a84d9d87cff2c84a8d4affb438f4e",
+                merkle_root: Some("ccbd66c6f7e8fdab47b3a486f59d28262be857f30d4773f2d5ea47f7761ce0e2"),
+                sighash_type: 0x00,
+                tweaked_private_key: "a8e7aa924f0d58854185a490e6c41f6efb7b675c0f3331b7f14b549400b4d501",
+                sig_hash: "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef",
+                witness: "b4010dd48a617db09926f729e79c33ae0b4e94b79f04a1ae93ede6315eb3669de185a17d2b0ac9ee09fd4c64b678a0b61a0a86fa888a273c8511be83bfd6810f",
+            },
+            KeyPathVector {
+                input_index: 6,
+                private_key: "415cfe9c15d9cea27d8104d5517c06e9de48e2f986b695e4f5ffebf230e725d8",
+                merkle_root: Some("2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def"),
+                sighash_type: 0x02,
+                tweaked_private_key: "241c14f2639d0d7139282aa6abde28dd8a067baa9d633e4e7230287ec2d02901",
+                sig_hash: "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85",
+                witness: "a3785919a2ce3c4ce26f298c3d51619bc474ae24014bcdd31328cd8cfbab2eff3395fa0a16fe5f486d12f22a9cedded5ae74feb4bbe5351346508c5405bcfee002",
+            },
+            KeyPathVector {
+                input_index: 7,
+                private_key: "c7b0e81f0a9a0b0499e112279d718cca98e79a12e2f137c72ae5b213aad0d103",
+                merkle_root: Some("6c2dc106ab816b73f9d07e3cd1ef2c8c1256f519748e0813e4edd2405d277bef"),
+                sighash_type: 0x82,
+                tweaked_private_key: "65b6000cd2bfa6b7cf736767a8955760e62b6649058cbc970b7c0871d786346b",
+                sig_hash: "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10",
+                witness: "ea0c6ba90763c2d3a296ad82ba45881abb4f426b3f87af162dd24d5109edc1cdd11915095ba47c3a9963dc1e6c432939872bc49212fe34c632cd3ab9fed429c482",
+            },
+            KeyPathVector {
+                input_index: 8,
+                private_key: "77863416be0d0665e517e1c375fd6f75839544eca553675ef7fdf4949518ebaa",
+                merkle_root: Some("ab179431c28d3b68fb798957faf5497d69c883c6fb1e1cd9f81483d87bac90cc"),
+                sighash_type: 0x81,
+                tweaked_private_key: "ec18ce6af99f43815db543f47b8af5ff5df3b2cb7315c955aa4a86e8143d2bf5",
+                sig_hash: "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2",
+                witness: "bbc9584a11074e83bc8c6759ec55401f0ae7b03ef290c3139814f545b58a9f8127258000874f44bc46db7646322107d4d86aec8e73b8719a61fff761d75b5dd981",
+            },
+            ];
+
+            // Script cannot hold the invalid output script, so it is replaced by an empty one
+            // Only the signatures not committing to that output stay valid: SIGHASH_NONE, and
+            // SIGHASH_SINGLE of inputs other than the second
+            let (unsigned, signed) = (
+                parse_tx(&UNSIGNED_TX.replace(INVALID_SCRIPT, "00")),
+                parse_tx(&SIGNED_TX.replace(INVALID_SCRIPT, "00")),
+            );
+            let commits_to_invalid_output =
+                |vector: &KeyPathVector| match vector.sighash_type & 0x03 {
+                    tx::SIGHASH_NONE => false,
+                    tx::SIGHASH_SINGLE => vector.input_index == 1,
+                    _ => true,
+                };
+
+            let prevouts: Vec<TxOut> = UTXOS_SPENT
+                .iter()
+                .map(|(script_pubkey, amount)| TxOut::new(*amount, script(script_pubkey)))
+                .collect();
+            let mut utxos = MemoryUtxoProvider::new();
+            for (tx_in, prevout) in signed.tx_ins.iter().zip(&prevouts) {
+                utxos.insert(tx_in.prev_tx, tx_in.prev_index, prevout.clone());
+            }
+
+            for vector in &vectors {
+                let sig_hash = unsigned
+                    .sig_hash_taproot(
+                        vector.input_index,
+                        &prevouts,
+                        vector.sighash_type,
+                        None,
+                        None,
+                    )
+                    .unwrap();
+                let verified = signed.verify_input(vector.input_index, &utxos);
+
+                if commits_to_invalid_output(vector) {
+                    assert_ne!(hex::encode(&sig_hash), vector.sig_hash);
+                    assert!(matches!(
+                        verified,
+                        Err(crate::Error::Script {
+                            opcode: None,
+                            error: ScriptError::SchnorrSig
+                        })
+                    ));
+                } else {
+                    assert_eq!(hex::encode(&sig_hash), vector.sig_hash);
+                    assert!(verified.is_ok());
+                }
+            }
+
+            // Signatures use no auxiliary randomness
+            for vector in &vectors[..2] {
+                let private_key = PrivateKey::new(U256::from_be_hex(vector.private_key)).unwrap();
+                let tweaked = taproot::tweak_private_key(
+                    &private_key,
+                    vector.merkle_root.map(bytes32).as_ref(),
+                )
+                .unwrap();
+                assert_eq!(
+                    tweaked.secret(),
+                    U256::from_be_hex(vector.tweaked_private_key)
+                );
+
+                let sig_hash = bytes32(vector.sig_hash);
+                let mut sig = tweaked
+                    .sign_schnorr(&sig_hash, &[0; 32])
+                    .unwrap()
+                    .serialize()
+                    .to_vec();
+                if vector.sighash_type != tx::SIGHASH_DEFAULT {
+                    sig.push(vector.sighash_type as u8);
+                }
+                assert_eq!(hex::encode(&sig), vector.witness);
+            }
+
+            // The sighash type byte is part of what is signed
+            let mut wrong_type = signed.clone();
+            wrong_type.tx_ins[0].witness[0][64] = tx::SIGHASH_SINGLE as u8 | 0x80;
+            assert!(matches!(
+                wrong_type.verify_input(0, &utxos),
+                Err(crate::Error::Script {
+                    opcode: None,
+                    error: ScriptError::SchnorrSig
+                })
+            ));
+        }
+
+        #[test]
+        fn script_path() {
+            let alice = PrivateKey::new(U256::from_u64(0xa11ce)).unwrap();
+            let bob = PrivateKey::new(U256::from_u64(0xb0b)).unwrap();
+            let internal = PrivateKey::new(U256::from_u64(0x1a7e)).unwrap();
+            let key = |private_key: &PrivateKey| {
+                Command::Data(private_key.x_only_public_key().serialize().to_vec())
+            };
+
+            let single = Script::new(vec![key(&alice), Command::Op(Opcode::OP_CHECKSIG)]);
+            let two_of_two = Script::new(vec![
+                key(&alice),
+                Command::Op(Opcode::OP_CHECKSIG),
+                key(&bob),
+                Command::Op(Opcode::OP_CHECKSIGADD),
+                Command::Op(Opcode::OP_2),
+                Command::Op(Opcode::OP_NUMEQUAL),
+            ]);
+            let multisig = Script::new(vec![
+                Command::Op(Opcode::OP_0),
+                Command::Op(Opcode::OP_0),
+                Command::Op(Opcode::OP_0),
+                Command::Op(Opcode::OP_CHECKMULTISIG),
+            ]);
+            // The same script as `single`, but pushing the key with OP_PUSHDATA1
+            let non_minimal = Script::from_raw(
+                [
+                    &[0x4c, 0x20],
+                    &alice.x_only_public_key().serialize()[..],
+                    &[0xac],
+                ]
+                .concat(),
+            );
+            let op_success = Script::new(vec![Command::Op(Opcode::OP_RESERVED)]);
+            let conditional = Script::new(vec![
+                Command::Op(Opcode::OP_IF),
+                Command::Op(Opcode::OP_1),
+                Command::Op(Opcode::OP_ENDIF),
+            ]);
+
+            let tree = TapTree::branch(
+                TapTree::branch(
+                    TapTree::leaf(single.clone()),
+                    TapTree::leaf(two_of_two.clone()),
+                ),
+                TapTree::branch(
+                    TapTree::leaf(multisig.clone()),
+                    TapTree::branch(
+                        TapTree::leaf(non_minimal.clone()),
+                        TapTree::branch(
+                            TapTree::leaf(op_success.clone()),
+                            TapTree::leaf(conditional.clone()),
+                        ),
+                    ),
+                ),
+            );
+            let merkle_root = tree.hash();
+            let internal_key = internal.x_only_public_key();
+            let (output_key, _) =
+                taproot::tweak_public_key(&internal_key, Some(&merkle_root)).unwrap();
+
+            let prevout = TxOut::new(50_000, Script::p2tr(&output_key));
+            let mut utxos = MemoryUtxoProvider::new();
+            utxos.insert([0x01; 32], 0, prevout.clone());
+            let tx = Tx {
+                version: 2,
+                tx_ins: vec![TxIn::new([0x01; 32], 0)],
+                tx_outs: vec![TxOut::new(49_000, Script::p2tr(&internal_key))],
+                locktime: 0,
+            };
+
+            let spend = |leaf: &Script, items: Vec<Vec<u8>>| {
+                let control_block = tree
+                    .control_block(&internal_key, taproot::TAPSCRIPT_LEAF_VERSION, leaf)
+                    .unwrap();
+                let mut tx = tx.clone();
+                tx.tx_ins[0].witness = items;
+                tx.tx_ins[0].witness.push(leaf.raw_serialize());
+                tx.tx_ins[0].witness.push(control_block.serialize());
+                tx.verify_input(0, &utxos)
+            };
+            let sign = |private_key: &PrivateKey, leaf: &Script| {
+                let leaf_hash =
+                    taproot::tap_leaf_hash(taproot::TAPSCRIPT_LEAF_VERSION, &leaf.raw_serialize());
+                let sig_hash = tx
+                    .sig_hash_taproot(
+                        0,
+                        std::slice::from_ref(&prevout),
+                        tx::SIGHASH_DEFAULT,
+                        None,
+                        Some((leaf_hash, u32::MAX)),
+                    )
+                    .unwrap();
+                private_key
+                    .sign_schnorr(&sig_hash, &[0; 32])
+                    .unwrap()
+                    .serialize()
+                    .to_vec()
+            };
+            let script_error = |result: Result<(), crate::Error>| match result {
+                Err(crate::Error::Script { error, .. }) => Some(error),
+                _ => None,
+            };
+
+            let alice_single = sign(&alice, &single);
+            assert!(spend(&single, vec![alice_single.clone()]).is_ok());
+            // An empty signature fails the check, an invalid one the whole script
+            assert_eq!(
+                script_error(spend(&single, vec![Vec::new()])),
+                Some(ScriptError::EvalFalse)
+            );
+            assert_eq!(
+                script_error(spend(&two_of_two, vec![Vec::new(), alice_single])),
+                Some(ScriptError::SchnorrSig)
+            );
+
+            // Signatures commit to the leaf as it is in the witness
+            assert!(spend(&non_minimal, vec![sign(&alice, &non_minimal)]).is_ok());
+            assert_eq!(
+                script_error(spend(&non_minimal, vec![sign(&alice, &single)])),
+                Some(ScriptError::SchnorrSig)
+            );
+
+            let alice_two = sign(&alice, &two_of_two);
+            let bob_two = sign(&bob, &two_of_two);
+            assert!(spend(&two_of_two, vec![bob_two, alice_two.clone()]).is_ok());
+            assert_eq!(
+                script_error(spend(&two_of_two, vec![Vec::new(), alice_two])),
+                Some(ScriptError::EvalFalse)
+            );
+
+            assert_eq!(
+                script_error(spend(&multisig, Vec::new())),
+                Some(ScriptError::TapscriptCheckMultisig)
+            );
+            assert!(spend(&op_success, Vec::new()).is_ok());
+            assert!(spend(&conditional, vec![vec![0x01]]).is_ok());
+            assert_eq!(
+                script_error(spend(&conditional, vec![vec![0x02]])),
+                Some(ScriptError::TapscriptMinimalIf)
+            );
+
+            // The control block of another leaf
+            let mut wrong_leaf = tx.clone();
+            wrong_leaf.tx_ins[0].witness = vec![
+                multisig.raw_serialize(),
+                tree.control_block(&internal_key, taproot::TAPSCRIPT_LEAF_VERSION, &op_success)
+                    .unwrap()
+                    .serialize(),
+            ];
+            assert_eq!(
+                script_error(wrong_leaf.verify_input(0, &utxos)),
+                Some(ScriptError::WitnessProgramMismatch)
+            );
+
+            // Key path, with an annex and an explicit sighash type
+            let annex = vec![0x50, 0x01, 0x02];
+            let sig_hash = tx
+                .sig_hash_taproot(
+                    0,
+                    std::slice::from_ref(&prevout),
+                    tx::SIGHASH_ALL,
+                    Some(&annex),
+                    None,
+                )
+                .unwrap();
+            let tweaked = taproot::tweak_private_key(&internal, Some(&merkle_root)).unwrap();
+            let mut sig = tweaked
+                .sign_schnorr(&sig_hash, &[0; 32])
+                .unwrap()
+                .serialize()
+                .to_vec();
+            sig.push(tx::SIGHASH_ALL as u8);
+
+            let mut key_path = tx.clone();
+            key_path.tx_ins[0].witness = vec![sig, annex];
+            assert!(key_path.verify(&utxos).is_ok());
+
+            key_path.tx_ins[0].witness[1][1] = 0xff;
+            assert_eq!(
+                script_error(key_path.verify_input(0, &utxos)),
+                Some(ScriptError::SchnorrSig)
+            );
+        }
+    }
+
+    mod block {
+        use crypto_bigint::U256;
+
+        use crate::{
+            block::{self, BlockHeader},
+            hex,
+        };
+
+        const GENESIS: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+        const HEADER: &str = "020000208ec39428b17323fa0ddec8e887b4a7c53b8c0a0a220cfd0000000000000000005b0750fce0a889502d40508d39576821155e9c9e3f5c3157f961db38fd8b25be1e77a759e93c0118a4ffd71d";
+
+        fn header(hex_header: &str) -> BlockHeader {
+            BlockHeader::parse(&mut hex::decode(hex_header).unwrap().as_slice()).unwrap()
+        }
+
+        #[test]
+        fn parse() {
+            let header = header(HEADER);
+
+            assert_eq!(header.version, 0x20000002);
+            assert_eq!(
+                hex::encode(&header.prev_block),
+                "000000000000000000fd0c220a0a8c3bc5a7b487e8c8de0dfa2373b12894c38e"
+            );
+            assert_eq!(
+                hex::encode(&header.merkle_root),
+                "be258bfd38db61f957315c3f9e9c5e15216857398d50402d5089a8e0fc50075b"
+            );
+            assert_eq!(header.timestamp, 0x59a7771e);
+            assert_eq!(header.bits, 0x18013ce9);
+            assert_eq!(header.nonce, 0x1dd7ffa4);
+
+            assert_eq!(hex::encode(&header.serialize()), HEADER);
+            assert!(BlockHeader::parse(&mut &hex::decode(HEADER).unwrap()[..79]).is_err());
+        }
+
+        #[test]
+        fn hash() {
+            assert_eq!(
+                header(HEADER).id(),
+                "0000000000000000007e9e4c586439b0cdbe13b1370bdd9435d76a644d047523"
+            );
+            assert_eq!(
+                header(GENESIS).id(),
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+            );
+        }
+
+        #[test]
+        fn target() {
+            let target = header(HEADER).target().unwrap();
+
+            assert_eq!(
+                hex::encode(&target.to_be_bytes()),
+                "0000000000000000013ce9000000000000000000000000000000000000000000"
+            );
+            assert_eq!(block::target_to_bits(target), 0x18013ce9);
+
+            // Expanded and compacted again, as in Bitcoin Core's arith_uint256 tests
+            let cases: [(u32, u64, u32); 7] = [
+                (0x00123456, 0, 0),
+                (0x01003456, 0, 0),
+                (0x01123456, 0x12, 0x01120000),
+                (0x02123456, 0x1234, 0x02123400),
+                (0x03123456, 0x123456, 0x03123456),
+                (0x04123456, 0x12345600, 0x04123456),
+                (0x05009234, 0x92340000, 0x05009234),
+            ];
+            for (bits, expected, compacted) in cases {
+                let target = block::bits_to_target(bits).unwrap();
+
+                assert_eq!(target, U256::from_u64(expected));
+                assert_eq!(block::target_to_bits(target), compacted);
+            }
+
+            // A mantissa with its high bit set gets an extra byte
+            assert_eq!(block::target_to_bits(U256::from_u64(0x80)), 0x02008000);
+
+            assert!(block::bits_to_target(0x04923456).is_err());
+            assert!(block::bits_to_target(0xff123456).is_err());
+            assert_eq!(block::bits_to_target(0x04800000).unwrap(), U256::ZERO);
+            assert_eq!(block::bits_to_target(0x23000000).unwrap(), U256::ZERO);
+            assert_eq!(block::bits_to_target(0xff800000).unwrap(), U256::ZERO);
+        }
+
+        #[test]
+        fn difficulty() {
+            assert_eq!(header(GENESIS).difficulty(), 1.0);
+            assert!((header(HEADER).difficulty() - 888171856257.3206).abs() < 1e-3);
+        }
+
+        #[test]
+        fn check_pow() {
+            assert!(header(GENESIS).check_pow());
+            assert!(header(HEADER).check_pow());
+
+            let mut header = header(HEADER);
+            header.nonce += 1;
+            assert!(!header.check_pow());
+
+            // Targets easier than the maximum are rejected even if the hash meets them
+            let mut genesis = self::header(GENESIS);
+            genesis.bits = 0x1e00ffff;
+            assert!(!genesis.check_pow());
+
+            // So is a zero target, whatever its exponent
+            for bits in [0x1d000000, 0x23000000, 0xff800000] {
+                genesis.bits = bits;
+                assert!(!genesis.check_pow());
+            }
+        }
+    }
+
+    mod hashes {
+        use crate::{
+            hashes::{self, Hash},
+            hex,
+        };
+
+        #[test]
+        fn sha256() {
+            let cases: [(&[u8], &str); 3] = [
+                (
+                    b"",
+                    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                ),
+                (
+                    b"abc",
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                ),
+                (
+                    b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                    "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                ),
+            ];
+
+            for (data, expected) in cases {
+                assert_eq!(hex::encode(&hashes::sha256(data)), expected);
+            }
+        }
+
+        #[test]
+        fn sha512() {
+            let cases: [(&[u8], &str); 2] = [
+                (
+                    b"",
+                    "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+                ),
+                (
+                    b"abc",
+                    "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+                ),
+            ];
+
+            for (data, expected) in cases {
+                assert_eq!(hex::encode(&hashes::sha512(data)), expected);
+            }
+        }
+
+        #[test]
+        fn ripemd160() {
+            let cases: [(&[u8], &str); 3] = [
+                (b"", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+                (b"abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+                (
+                    b"message digest",
+                    "5d0689ef49d2fae572b881b123a85ffa21595f36",
+                ),
+            ];
+
+            for (data, expected) in cases {
+                assert_eq!(hex::encode(&hashes::ripemd160(data)), expected);
+            }
+        }
+
+        #[test]
+        fn sha1() {
+            let cases: [(&[u8], &str); 3] = [
+                (b"", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+                (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+                (
+                    b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                    "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+                ),
+            ];
+
+            for (data, expected) in cases {
+                assert_eq!(hex::encode(&hashes::sha1(data)), expected);
+            }
+        }
+
+        #[test]
+        fn streaming() {
+            // One million 'a', fed in uneven chunks
+            let data = vec![b'a'; 1_000_000];
+
+            let mut sha256 = hashes::Sha256::new();
+            let mut ripemd160 = hashes::Ripemd160::new();
+            for chunk in data.chunks(997) {
+                sha256.update(chunk);
+                ripemd160.update(chunk);
+            }
+
+            assert_eq!(
+                hex::encode(&sha256.finalize()),
+                "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+            );
+            assert_eq!(
+                hex::encode(&ripemd160.finalize()),
+                "52783243c1697bdbe16d37f97f68f08325dc1528"
+            );
+
+            let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+            for chunk_size in [1, 63, 64, 65, 127, 128, 129] {
+                let mut sha512 = hashes::Sha512::new();
+                let mut hash256 = hashes::Hash256::new();
+                for chunk in data.chunks(chunk_size) {
+                    sha512.update(chunk);
+                    hash256.update(chunk);
+                }
+
+                assert_eq!(sha512.finalize(), hashes::sha512(&data));
+                assert_eq!(hash256.finalize(), hashes::hash256(&data));
+            }
+        }
+
+        #[test]
+        fn hash256_and_hash160() {
+            assert_eq!(
+                hex::encode(&hashes::hash256(b"")),
+                "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+            );
+
+            // Compressed SEC of the generator point
+            let sec =
+                hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                    .unwrap();
+            assert_eq!(
+                hex::encode(&hashes::hash160(&sec)),
+                "751e76e8199196d454941c45d1b3a323f1433bd6"
+            );
+        }
+
+        #[test]
+        fn hmac() {
+            // RFC 4231 test cases 1, 2 and 6
+            let cases: [(Vec<u8>, &[u8], &str, &str); 3] = [
+                (
+                    vec![0x0b; 20],
+                    b"Hi There",
+                    "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+                    "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+                ),
+                (
+                    b"Jefe".to_vec(),
+                    b"what do ya want for nothing?",
+                    "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+                    "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+                ),
+                (
+                    vec![0xaa; 131],
+                    b"Test Using Larger Than Block-Size Key - Hash Key First",
+                    "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+                    "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+                ),
+            ];
+
+            for (key, data, expected_sha256, expected_sha512) in cases {
+                assert_eq!(
+                    hex::encode(&hashes::hmac_sha256(&key, data)),
+                    expected_sha256
+                );
+                assert_eq!(
+                    hex::encode(&hashes::hmac_sha512(&key, data)),
+                    expected_sha512
+                );
+            }
+        }
+
+        #[test]
+        fn tagged_hash() {
+            let tag_hash = hashes::sha256(b"BIP0340/challenge");
+            let data = b"data";
+
+            let expected = hashes::sha256(&[&tag_hash[..], &tag_hash, data].concat());
+
+            assert_eq!(hashes::tagged_hash("BIP0340/challenge", data), expected);
+        }
     }
 }
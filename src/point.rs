@@ -131,7 +131,43 @@ where
                 result = result + current;
             }
             current = current + current;
-            coef = coef >> 1;
+            coef >>= 1;
+        }
+
+        result
+    }
+
+    pub fn scalar_mul_u256(self, scalar: crypto_bigint::U256) -> Point<C> {
+        let mut current = self;
+        let mut result = Point::Infinity;
+
+        for i in 0..scalar.bits_vartime() {
+            if scalar.bit_vartime(i) {
+                result = result + current;
+            }
+            current = current + current;
+        }
+
+        result
+    }
+
+    /// The sum of `point * scalar` over `terms` by interleaved double-and-add: one chain of
+    /// doublings is shared between all of them, with an addition per set bit of every scalar.
+    pub fn multi_scalar_mul(terms: &[(Point<C>, crypto_bigint::U256)]) -> Point<C> {
+        let bits = terms
+            .iter()
+            .map(|(_, scalar)| scalar.bits_vartime())
+            .max()
+            .unwrap_or(0);
+        let mut result = Point::Infinity;
+
+        for i in (0..bits).rev() {
+            result = result + result;
+            for (point, scalar) in terms {
+                if scalar.bit_vartime(i) {
+                    result = result + *point;
+                }
+            }
         }
 
         result
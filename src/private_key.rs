@@ -0,0 +1,187 @@
+use crypto_bigint::U256;
+
+use crate::{
+    base58, hashes,
+    point::Point,
+    rfc6979,
+    schnorr::{self, SchnorrSignature, XOnlyPublicKey},
+    secp256k1::{self, PublicKey, G, N},
+    signature::Signature,
+    Network,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PrivateKey {
+    secret: U256,
+    point: PublicKey,
+}
+
+impl PrivateKey {
+    pub fn new(secret: U256) -> Result<Self, crate::Error> {
+        if secret == U256::ZERO || secret >= N {
+            return Err(crate::Error::ValueError(
+                "Secret must be between 1 and N - 1".to_string(),
+            ));
+        }
+
+        Ok(PrivateKey {
+            secret,
+            point: G.scalar_mul_u256(secret),
+        })
+    }
+
+    pub fn secret(&self) -> U256 {
+        self.secret
+    }
+
+    pub fn point(&self) -> PublicKey {
+        self.point
+    }
+
+    pub fn x_only_public_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from_point(&self.point).expect("the secret is not zero")
+    }
+
+    /// Wallet Import Format: Base58Check of the network prefix, the secret and, for keys whose
+    /// public key is serialized compressed, a 0x01 suffix.
+    pub fn wif(&self, compressed: bool, network: Network) -> String {
+        let mut payload = vec![network.wif_prefix()];
+        payload.extend_from_slice(&self.secret.to_be_bytes());
+        if compressed {
+            payload.push(0x01);
+        }
+
+        base58::encode_check(&payload)
+    }
+
+    /// Parses a WIF string, returning the key, whether its public key is compressed and the
+    /// network it belongs to.
+    pub fn parse_wif(wif: &str) -> Result<(Self, bool, Network), crate::Error> {
+        let payload = base58::decode_check(wif)?;
+
+        let network = match payload.first() {
+            Some(0x80) => Network::Mainnet,
+            Some(0xef) => Network::Testnet,
+            _ => return Err(crate::Error::ValueError("Invalid WIF prefix".to_string())),
+        };
+
+        let compressed = match payload.len() {
+            33 => false,
+            34 if payload[33] == 0x01 => true,
+            34 => {
+                return Err(crate::Error::ValueError(
+                    "Invalid WIF compression suffix".to_string(),
+                ))
+            }
+            len => {
+                return Err(crate::Error::ValueError(format!(
+                    "Invalid WIF payload length {len}"
+                )))
+            }
+        };
+
+        let private_key = PrivateKey::new(U256::from_be_slice(&payload[1..33]))?;
+
+        Ok((private_key, compressed, network))
+    }
+
+    /// The RFC 6979 nonce for signing `z` with this key.
+    pub fn deterministic_k(&self, z: U256) -> U256 {
+        rfc6979::generate_k(self.secret, z, N)
+    }
+
+    pub fn sign(&self, z: U256) -> Result<Signature, crate::Error> {
+        self.sign_with_k(z, self.deterministic_k(z))
+    }
+
+    /// Signs `z` with RFC 6979 nonces, adding a counter as extra data until `r` is low, as
+    /// Bitcoin Core does to save a byte per signature.
+    pub fn sign_low_r(&self, z: U256) -> Result<Signature, crate::Error> {
+        let mut signature = self.sign(z)?;
+
+        let mut counter = 0u32;
+        while !signature.is_low_r() {
+            counter += 1;
+
+            let mut extra = [0u8; 32];
+            extra[..4].copy_from_slice(&counter.to_le_bytes());
+
+            let k = rfc6979::generate_k_with_data(self.secret, z, N, &extra);
+            signature = self.sign_with_k(z, k)?;
+        }
+
+        Ok(signature)
+    }
+
+    /// Signs `z` using `k` as the nonce. `k` must be unique per signature and kept secret,
+    /// otherwise the private key can be recovered from the signatures. The resulting signature
+    /// is always low-S.
+    pub fn sign_with_k(&self, z: U256, k: U256) -> Result<Signature, crate::Error> {
+        let k = secp256k1::scalar(k);
+
+        let Point::Real(x, _) = G.scalar_mul_u256(k.num()) else {
+            return Err(crate::Error::ValueError(
+                "k must be between 1 and N - 1".to_string(),
+            ));
+        };
+        let r = secp256k1::scalar(x.num());
+
+        let s = secp256k1::scalar(z)
+            .add(r.mul(secp256k1::scalar(self.secret))?)?
+            .mul(k.inverse()?)?;
+
+        if r.num() == U256::ZERO || s.num() == U256::ZERO {
+            return Err(crate::Error::ValueError(
+                "k produced an invalid signature".to_string(),
+            ));
+        }
+
+        Ok(Signature::new(r.num(), s.num()).normalize())
+    }
+
+    /// BIP-340 Schnorr signature of `msg`. `aux_rand` should be fresh randomness, which guards
+    /// the nonce against side channels; signing stays safe if it is not.
+    pub fn sign_schnorr(
+        &self,
+        msg: &[u8],
+        aux_rand: &[u8; 32],
+    ) -> Result<SchnorrSignature, crate::Error> {
+        // Sign with the secret of the even-y point the x-only key stands for
+        let d = if self.point.has_even_y() {
+            self.secret
+        } else {
+            N - self.secret
+        };
+        let public_key = self.x_only_public_key();
+
+        let aux = hashes::tagged_hash("BIP0340/aux", aux_rand);
+        let t: Vec<u8> = schnorr::bytes32(d)
+            .iter()
+            .zip(aux)
+            .map(|(d, aux)| d ^ aux)
+            .collect();
+        let nonce = hashes::tagged_hash(
+            "BIP0340/nonce",
+            &[&t[..], &public_key.serialize(), msg].concat(),
+        );
+
+        let k = secp256k1::scalar(U256::from_be_slice(&nonce)).num();
+        let r_point = G.scalar_mul_u256(k);
+        let Point::Real(r, _) = r_point else {
+            return Err(crate::Error::ValueError("The nonce is zero".to_string()));
+        };
+        let k = if r_point.has_even_y() { k } else { N - k };
+
+        let e = schnorr::challenge(r.num(), &public_key.serialize(), msg);
+        let s = secp256k1::scalar(k).add(secp256k1::scalar(e).mul(secp256k1::scalar(d))?)?;
+
+        let signature = SchnorrSignature::new(r.num(), s.num());
+        if !public_key.verify(msg, &signature) {
+            return Err(crate::Error::ValueError(
+                "Produced an invalid signature".to_string(),
+            ));
+        }
+
+        Ok(signature)
+    }
+}
@@ -0,0 +1,37 @@
+use crypto_bigint::U256;
+
+use crate::hashes::hmac_sha256;
+
+/// Derives the nonce `k` for signing `z` with `secret` over a group of order `order`, as
+/// specified in RFC 6979 section 3.2, using HMAC-SHA256. `order` must be 256 bits long.
+pub fn generate_k(secret: U256, z: U256, order: U256) -> U256 {
+    generate_k_with_data(secret, z, order, &[])
+}
+
+/// Like `generate_k`, with `extra` mixed into the seed as described in RFC 6979 section 3.6.
+pub fn generate_k_with_data(secret: U256, z: U256, order: U256, extra: &[u8]) -> U256 {
+    let order_nz = crypto_bigint::NonZero::new(order).unwrap();
+
+    let x = secret.to_be_bytes();
+    let h = z.rem_vartime(&order_nz).to_be_bytes();
+
+    let mut k = [0u8; 32];
+    let mut v = [1u8; 32];
+
+    k = hmac_sha256(&k, &[&v[..], &[0x00], &x, &h, extra].concat());
+    v = hmac_sha256(&k, &v);
+    k = hmac_sha256(&k, &[&v[..], &[0x01], &x, &h, extra].concat());
+    v = hmac_sha256(&k, &v);
+
+    loop {
+        v = hmac_sha256(&k, &v);
+        let candidate = U256::from_be_slice(&v);
+
+        if candidate != U256::ZERO && candidate < order {
+            return candidate;
+        }
+
+        k = hmac_sha256(&k, &[&v[..], &[0x00]].concat());
+        v = hmac_sha256(&k, &v);
+    }
+}
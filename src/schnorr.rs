@@ -0,0 +1,188 @@
+use crypto_bigint::U256;
+
+use crate::{
+    hashes::{self, Hash, Sha256},
+    point::Point,
+    secp256k1::{self, PublicKey, S256Field, G, N, P},
+};
+
+/// A BIP-340 public key: only the x coordinate is kept, and it stands for the point with that
+/// x and an even y.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct XOnlyPublicKey(PublicKey);
+
+impl XOnlyPublicKey {
+    /// The x-only key with the same x as `point`. If `point` has an odd y, the key is its
+    /// negation.
+    pub fn from_point(point: &PublicKey) -> Result<Self, crate::Error> {
+        match point {
+            Point::Real(_, _) if point.has_even_y() => Ok(XOnlyPublicKey(*point)),
+            Point::Real(x, y) => Ok(XOnlyPublicKey(Point::Real(*x, S256Field::new(P - y.num())))),
+            Point::Infinity => Err(crate::Error::ValueError(
+                "The point at infinity has no x coordinate".to_string(),
+            )),
+        }
+    }
+
+    /// Parses 32 bytes of x coordinate, failing if no point on the curve has it.
+    pub fn parse(bytes: &[u8]) -> Result<Self, crate::Error> {
+        if bytes.len() != 32 {
+            return Err(crate::Error::ValueError(format!(
+                "Invalid x-only public key length {}",
+                bytes.len()
+            )));
+        }
+
+        Point::parse_sec(&[&[0x02], bytes].concat()).map(XOnlyPublicKey)
+    }
+
+    pub fn serialize(&self) -> [u8; 32] {
+        let Point::Real(x, _) = self.0 else {
+            unreachable!("x-only keys are never the point at infinity")
+        };
+
+        bytes32(x.num())
+    }
+
+    /// The point with even y the key stands for.
+    pub fn point(&self) -> PublicKey {
+        self.0
+    }
+
+    /// Checks a BIP-340 signature of `msg`, which may be of any length.
+    pub fn verify(&self, msg: &[u8], sig: &SchnorrSignature) -> bool {
+        if sig.r >= P || sig.s >= N {
+            return false;
+        }
+
+        let e = challenge(sig.r, &self.serialize(), msg);
+        let minus_e = secp256k1::scalar(N - e).num();
+
+        let total = G.scalar_mul_u256(sig.s) + self.0.scalar_mul_u256(minus_e);
+
+        match total {
+            Point::Real(x, _) => total.has_even_y() && x.num() == sig.r,
+            Point::Infinity => false,
+        }
+    }
+}
+
+/// Checks many BIP-340 signatures at once, faster than checking them one by one. Returns the
+/// index of the first invalid signature if any is.
+///
+/// Each signature satisfies `s * G = R + e * P`. Rather than checking every equation, this
+/// checks their sum with each equation multiplied by a random factor, so that invalid
+/// signatures cannot cancel each other out. The factors are derived from a hash of the whole
+/// batch, as BIP-340 suggests. If the sum does not hold, the signatures are checked one by one
+/// to find the invalid one.
+pub fn batch_verify(batch: &[(XOnlyPublicKey, &[u8], SchnorrSignature)]) -> Result<(), usize> {
+    if batch_equation_holds(batch) {
+        return Ok(());
+    }
+
+    match batch
+        .iter()
+        .position(|(public_key, msg, sig)| !public_key.verify(msg, sig))
+    {
+        Some(index) => Err(index),
+        None => unreachable!("the batch equation holds when all signatures are valid"),
+    }
+}
+
+fn batch_equation_holds(batch: &[(XOnlyPublicKey, &[u8], SchnorrSignature)]) -> bool {
+    let mut engine = Sha256::tagged(b"BIP0340/batch");
+    for (public_key, msg, sig) in batch {
+        engine.update(&public_key.serialize());
+        engine.update(&(msg.len() as u64).to_le_bytes());
+        engine.update(msg);
+        engine.update(&sig.serialize());
+    }
+    let seed = engine.finalize();
+
+    let mut s_sum = secp256k1::scalar(U256::ZERO);
+    let mut terms = Vec::with_capacity(2 * batch.len() + 1);
+
+    for (i, (public_key, msg, sig)) in batch.iter().enumerate() {
+        if sig.r >= P || sig.s >= N {
+            return false;
+        }
+        let Ok(r) = XOnlyPublicKey::parse(&bytes32(sig.r)) else {
+            return false;
+        };
+
+        // The first factor can be 1 without weakening the check
+        let a = if i == 0 {
+            secp256k1::scalar(U256::ONE)
+        } else {
+            let hash = hashes::sha256(&[&seed[..], &(i as u64).to_le_bytes()].concat());
+            secp256k1::scalar(U256::from_be_slice(&hash))
+        };
+        let e = secp256k1::scalar(challenge(sig.r, &public_key.serialize(), msg));
+        let a_e = a.mul(e).expect("same prime");
+
+        s_sum = s_sum
+            .add(a.mul(secp256k1::scalar(sig.s)).expect("same prime"))
+            .expect("same prime");
+        terms.push((r.point(), secp256k1::scalar(N - a.num()).num()));
+        terms.push((public_key.point(), secp256k1::scalar(N - a_e.num()).num()));
+    }
+
+    // (sum of a * s) * G - sum of (a * R + a * e * P) is infinity when every equation holds
+    terms.push((G, s_sum.num()));
+
+    Point::multi_scalar_mul(&terms) == Point::Infinity
+}
+
+/// A BIP-340 signature: the x coordinate of the nonce point `R`, and `s`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SchnorrSignature {
+    pub r: U256,
+    pub s: U256,
+}
+
+impl SchnorrSignature {
+    pub fn new(r: U256, s: U256) -> Self {
+        SchnorrSignature { r, s }
+    }
+
+    /// `r || s`, 32 bytes each.
+    pub fn serialize(&self) -> [u8; 64] {
+        let mut result = [0u8; 64];
+        result[..32].copy_from_slice(&bytes32(self.r));
+        result[32..].copy_from_slice(&bytes32(self.s));
+
+        result
+    }
+
+    /// Parses the 64 byte serialization. Out of range values are left for `verify` to reject.
+    pub fn parse(bytes: &[u8]) -> Result<Self, crate::Error> {
+        if bytes.len() != 64 {
+            return Err(crate::Error::ValueError(format!(
+                "Invalid Schnorr signature length {}",
+                bytes.len()
+            )));
+        }
+
+        Ok(SchnorrSignature::new(
+            U256::from_be_slice(&bytes[..32]),
+            U256::from_be_slice(&bytes[32..]),
+        ))
+    }
+}
+
+/// `e`, the hash committing a signature to its nonce, public key and message, modulo `N`.
+pub(crate) fn challenge(r: U256, public_key: &[u8; 32], msg: &[u8]) -> U256 {
+    let hash = hashes::tagged_hash(
+        "BIP0340/challenge",
+        &[&bytes32(r)[..], public_key, msg].concat(),
+    );
+
+    secp256k1::scalar(U256::from_be_slice(&hash)).num()
+}
+
+pub(crate) fn bytes32(num: U256) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&num.to_be_bytes());
+
+    result
+}
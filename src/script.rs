@@ -0,0 +1,1511 @@
+use std::io::Read;
+
+use crypto_bigint::U256;
+
+use crate::{
+    base58, bech32, hashes, hex,
+    schnorr::{SchnorrSignature, XOnlyPublicKey},
+    secp256k1::PublicKey,
+    signature::Signature,
+    taproot::{self, ControlBlock, TAPSCRIPT_LEAF_VERSION},
+    varint, Network,
+};
+
+macro_rules! opcodes {
+    ($($name:ident = $byte:literal,)+) => {
+        /// Every opcode except direct data pushes (0x01 to 0x4b), which are represented as
+        /// `Command::Data`.
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub enum Opcode {
+            $($name,)+
+            /// An opcode byte without a defined meaning (0xbb to 0xfe).
+            OP_UNKNOWN(u8),
+        }
+
+        impl Opcode {
+            /// `None` for bytes that push data directly.
+            pub fn from_byte(byte: u8) -> Option<Opcode> {
+                match byte {
+                    $($byte => Some(Opcode::$name),)+
+                    0x01..=0x4b => None,
+                    byte => Some(Opcode::OP_UNKNOWN(byte)),
+                }
+            }
+
+            pub fn to_byte(self) -> u8 {
+                match self {
+                    $(Opcode::$name => $byte,)+
+                    Opcode::OP_UNKNOWN(byte) => byte,
+                }
+            }
+
+            pub fn name(self) -> String {
+                match self {
+                    $(Opcode::$name => stringify!($name).to_string(),)+
+                    Opcode::OP_UNKNOWN(byte) => format!("OP_UNKNOWN_{byte:#04x}"),
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    OP_0 = 0x00,
+    OP_PUSHDATA1 = 0x4c,
+    OP_PUSHDATA2 = 0x4d,
+    OP_PUSHDATA4 = 0x4e,
+    OP_1NEGATE = 0x4f,
+    OP_RESERVED = 0x50,
+    OP_1 = 0x51,
+    OP_2 = 0x52,
+    OP_3 = 0x53,
+    OP_4 = 0x54,
+    OP_5 = 0x55,
+    OP_6 = 0x56,
+    OP_7 = 0x57,
+    OP_8 = 0x58,
+    OP_9 = 0x59,
+    OP_10 = 0x5a,
+    OP_11 = 0x5b,
+    OP_12 = 0x5c,
+    OP_13 = 0x5d,
+    OP_14 = 0x5e,
+    OP_15 = 0x5f,
+    OP_16 = 0x60,
+    OP_NOP = 0x61,
+    OP_VER = 0x62,
+    OP_IF = 0x63,
+    OP_NOTIF = 0x64,
+    OP_VERIF = 0x65,
+    OP_VERNOTIF = 0x66,
+    OP_ELSE = 0x67,
+    OP_ENDIF = 0x68,
+    OP_VERIFY = 0x69,
+    OP_RETURN = 0x6a,
+    OP_TOALTSTACK = 0x6b,
+    OP_FROMALTSTACK = 0x6c,
+    OP_2DROP = 0x6d,
+    OP_2DUP = 0x6e,
+    OP_3DUP = 0x6f,
+    OP_2OVER = 0x70,
+    OP_2ROT = 0x71,
+    OP_2SWAP = 0x72,
+    OP_IFDUP = 0x73,
+    OP_DEPTH = 0x74,
+    OP_DROP = 0x75,
+    OP_DUP = 0x76,
+    OP_NIP = 0x77,
+    OP_OVER = 0x78,
+    OP_PICK = 0x79,
+    OP_ROLL = 0x7a,
+    OP_ROT = 0x7b,
+    OP_SWAP = 0x7c,
+    OP_TUCK = 0x7d,
+    OP_CAT = 0x7e,
+    OP_SUBSTR = 0x7f,
+    OP_LEFT = 0x80,
+    OP_RIGHT = 0x81,
+    OP_SIZE = 0x82,
+    OP_INVERT = 0x83,
+    OP_AND = 0x84,
+    OP_OR = 0x85,
+    OP_XOR = 0x86,
+    OP_EQUAL = 0x87,
+    OP_EQUALVERIFY = 0x88,
+    OP_RESERVED1 = 0x89,
+    OP_RESERVED2 = 0x8a,
+    OP_1ADD = 0x8b,
+    OP_1SUB = 0x8c,
+    OP_2MUL = 0x8d,
+    OP_2DIV = 0x8e,
+    OP_NEGATE = 0x8f,
+    OP_ABS = 0x90,
+    OP_NOT = 0x91,
+    OP_0NOTEQUAL = 0x92,
+    OP_ADD = 0x93,
+    OP_SUB = 0x94,
+    OP_MUL = 0x95,
+    OP_DIV = 0x96,
+    OP_MOD = 0x97,
+    OP_LSHIFT = 0x98,
+    OP_RSHIFT = 0x99,
+    OP_BOOLAND = 0x9a,
+    OP_BOOLOR = 0x9b,
+    OP_NUMEQUAL = 0x9c,
+    OP_NUMEQUALVERIFY = 0x9d,
+    OP_NUMNOTEQUAL = 0x9e,
+    OP_LESSTHAN = 0x9f,
+    OP_GREATERTHAN = 0xa0,
+    OP_LESSTHANOREQUAL = 0xa1,
+    OP_GREATERTHANOREQUAL = 0xa2,
+    OP_MIN = 0xa3,
+    OP_MAX = 0xa4,
+    OP_WITHIN = 0xa5,
+    OP_RIPEMD160 = 0xa6,
+    OP_SHA1 = 0xa7,
+    OP_SHA256 = 0xa8,
+    OP_HASH160 = 0xa9,
+    OP_HASH256 = 0xaa,
+    OP_CODESEPARATOR = 0xab,
+    OP_CHECKSIG = 0xac,
+    OP_CHECKSIGVERIFY = 0xad,
+    OP_CHECKMULTISIG = 0xae,
+    OP_CHECKMULTISIGVERIFY = 0xaf,
+    OP_NOP1 = 0xb0,
+    OP_CHECKLOCKTIMEVERIFY = 0xb1,
+    OP_CHECKSEQUENCEVERIFY = 0xb2,
+    OP_NOP4 = 0xb3,
+    OP_NOP5 = 0xb4,
+    OP_NOP6 = 0xb5,
+    OP_NOP7 = 0xb6,
+    OP_NOP8 = 0xb7,
+    OP_NOP9 = 0xb8,
+    OP_NOP10 = 0xb9,
+    OP_CHECKSIGADD = 0xba,
+    OP_INVALIDOPCODE = 0xff,
+}
+
+impl std::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    Op(Opcode),
+    Data(Vec<u8>),
+}
+
+/// A script, kept as the bytes it was parsed from so that it serializes and hashes exactly as
+/// it was received. Its commands are decoded as they are needed.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Script {
+    raw: Vec<u8>,
+}
+
+impl Script {
+    /// Encodes `cmds`, pushing data with the shortest prefix that fits it.
+    pub fn new(cmds: Vec<Command>) -> Self {
+        let mut raw = Vec::new();
+
+        for cmd in &cmds {
+            match cmd {
+                Command::Op(op) => raw.push(op.to_byte()),
+                Command::Data(data) => {
+                    let len = data.len();
+                    if len <= 0x4b {
+                        raw.push(len as u8);
+                    } else if len <= 0xff {
+                        raw.push(Opcode::OP_PUSHDATA1.to_byte());
+                        raw.push(len as u8);
+                    } else if len <= 0xffff {
+                        raw.push(Opcode::OP_PUSHDATA2.to_byte());
+                        raw.extend_from_slice(&(len as u16).to_le_bytes());
+                    } else {
+                        raw.push(Opcode::OP_PUSHDATA4.to_byte());
+                        raw.extend_from_slice(&(len as u32).to_le_bytes());
+                    }
+                    raw.extend_from_slice(data);
+                }
+            }
+        }
+
+        Script { raw }
+    }
+
+    /// Wraps raw script bytes without decoding them. Scripts that do not decode, like those
+    /// ending in a truncated push, are valid in transactions and only fail when run.
+    pub fn from_raw(raw: Vec<u8>) -> Self {
+        Script { raw }
+    }
+
+    /// The decoded commands, failing at the first one that does not decode.
+    pub fn cmds(&self) -> Result<Vec<Command>, crate::Error> {
+        self.instructions()
+            .map(|ins| ins.map(|(cmd, _)| cmd))
+            .collect()
+    }
+
+    /// Decodes the commands one at a time, with the offset each one ends at.
+    pub(crate) fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            raw: &self.raw,
+            rest: &self.raw,
+        }
+    }
+
+    /// Removes every occurrence of `pattern` that starts at a command boundary. Legacy signature
+    /// checks remove the push of the signature from the script code this way, since a signature
+    /// cannot sign itself.
+    pub(crate) fn find_and_delete(&self, pattern: &Script) -> Script {
+        let pattern = &pattern.raw;
+        if pattern.is_empty() {
+            return self.clone();
+        }
+
+        let mut raw = Vec::new();
+        let (mut start, mut pc) = (0, 0);
+        loop {
+            raw.extend_from_slice(&self.raw[start..pc]);
+            while self.raw[pc..].starts_with(pattern) {
+                pc += pattern.len();
+            }
+            start = pc;
+
+            let mut instructions = Instructions {
+                raw: &self.raw,
+                rest: &self.raw[pc..],
+            };
+            match instructions.next() {
+                Some(Ok((_, end))) => pc = end,
+                _ => break,
+            }
+        }
+        raw.extend_from_slice(&self.raw[start..]);
+
+        Script { raw }
+    }
+
+    /// The script with every `OP_CODESEPARATOR` removed, as legacy signature hashes serialize
+    /// it. Pushes keep their encoding, and a command that does not decode is kept as it is,
+    /// along with everything after it.
+    pub(crate) fn remove_codeseparators(&self) -> Script {
+        let mut raw = Vec::new();
+        let mut start = 0;
+
+        for ins in self.instructions() {
+            match ins {
+                Ok((Command::Op(Opcode::OP_CODESEPARATOR), end)) => start = end,
+                Ok((_, end)) => {
+                    raw.extend_from_slice(&self.raw[start..end]);
+                    start = end;
+                }
+                Err(_) => {
+                    raw.extend_from_slice(&self.raw[start..]);
+                    break;
+                }
+            }
+        }
+
+        Script { raw }
+    }
+
+    /// `OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG`
+    pub fn p2pkh(hash: &[u8; 20]) -> Self {
+        Script::new(vec![
+            Command::Op(Opcode::OP_DUP),
+            Command::Op(Opcode::OP_HASH160),
+            Command::Data(hash.to_vec()),
+            Command::Op(Opcode::OP_EQUALVERIFY),
+            Command::Op(Opcode::OP_CHECKSIG),
+        ])
+    }
+
+    /// The public key hash of a P2PKH script.
+    pub fn p2pkh_hash(&self) -> Option<[u8; 20]> {
+        match self.raw.as_slice() {
+            [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] => hash.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// `OP_HASH160 <hash> OP_EQUAL`, where `hash` is the hash160 of the redeem script.
+    pub fn p2sh(hash: &[u8; 20]) -> Self {
+        Script::new(vec![
+            Command::Op(Opcode::OP_HASH160),
+            Command::Data(hash.to_vec()),
+            Command::Op(Opcode::OP_EQUAL),
+        ])
+    }
+
+    /// The redeem script hash of a P2SH script. Matched on the exact bytes, like consensus
+    /// does, so a non-minimal push of the hash is not P2SH.
+    pub fn p2sh_hash(&self) -> Option<[u8; 20]> {
+        match self.raw.as_slice() {
+            [0xa9, 0x14, hash @ .., 0x87] => hash.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// `m` of `n` bare multisig, usually wrapped in P2SH. Public keys are compressed and kept in
+    /// the given order, which signatures must follow.
+    pub fn multisig(m: usize, public_keys: &[PublicKey]) -> Result<Self, crate::Error> {
+        let n = public_keys.len();
+        if !(1..=n).contains(&m) || n > 16 {
+            return Err(crate::Error::ValueError(format!(
+                "Invalid {m} of {n} multisig"
+            )));
+        }
+
+        let small_int = |i: usize| {
+            Command::Op(
+                Opcode::from_byte(Opcode::OP_1.to_byte() + i as u8 - 1).expect("OP_1 to OP_16"),
+            )
+        };
+
+        let mut cmds = vec![small_int(m)];
+        cmds.extend(public_keys.iter().map(|key| Command::Data(key.sec(true))));
+        cmds.push(small_int(n));
+        cmds.push(Command::Op(Opcode::OP_CHECKMULTISIG));
+
+        Ok(Script::new(cmds))
+    }
+
+    /// The P2SH script paying to this redeem script.
+    pub fn to_p2sh(&self) -> Script {
+        Script::p2sh(&hashes::hash160(&self.raw_serialize()))
+    }
+
+    /// The "3..." (or "2..." on testnet) address of this redeem script.
+    pub fn p2sh_address(&self, network: Network) -> String {
+        self.to_p2sh()
+            .address(network)
+            .expect("P2SH scripts have addresses")
+    }
+
+    /// The address this script pays to, if it is P2PKH, P2SH or a witness program.
+    pub fn address(&self, network: Network) -> Option<String> {
+        if let Some((version, program)) = self.witness_program() {
+            return bech32::encode_segwit(network.bech32_hrp(), version, program).ok();
+        }
+
+        let (prefix, hash) = match (self.p2pkh_hash(), self.p2sh_hash()) {
+            (Some(hash), _) => (network.p2pkh_prefix(), hash),
+            (_, Some(hash)) => (network.p2sh_prefix(), hash),
+            _ => return None,
+        };
+
+        Some(base58::encode_check(&[&[prefix], &hash[..]].concat()))
+    }
+
+    /// The script that pays to `address`.
+    pub fn from_address(address: &str) -> Result<Self, crate::Error> {
+        for network in [Network::Mainnet, Network::Testnet] {
+            let hrp = network.bech32_hrp();
+            if address.to_ascii_lowercase().starts_with(&format!("{hrp}1")) {
+                let (version, program) = bech32::decode_segwit(hrp, address)?;
+                return Script::from_witness_program(version, &program);
+            }
+        }
+
+        let payload = base58::decode_check(address)?;
+        let unsupported = || crate::Error::ValueError(format!("Unsupported address {address}"));
+
+        let (prefix, hash) = payload.split_first().ok_or_else(unsupported)?;
+        let hash: &[u8; 20] = hash.try_into().map_err(|_| unsupported())?;
+
+        for network in [Network::Mainnet, Network::Testnet] {
+            if *prefix == network.p2pkh_prefix() {
+                return Ok(Script::p2pkh(hash));
+            }
+            if *prefix == network.p2sh_prefix() {
+                return Ok(Script::p2sh(hash));
+            }
+        }
+
+        Err(unsupported())
+    }
+
+    /// `OP_0 <hash>`, with the 20 byte hash160 of a public key.
+    pub fn p2wpkh(hash: &[u8; 20]) -> Self {
+        Script::new(vec![
+            Command::Op(Opcode::OP_0),
+            Command::Data(hash.to_vec()),
+        ])
+    }
+
+    /// `OP_0 <hash>`, with the 32 byte SHA-256 of a witness script.
+    pub fn p2wsh(hash: &[u8; 32]) -> Self {
+        Script::new(vec![
+            Command::Op(Opcode::OP_0),
+            Command::Data(hash.to_vec()),
+        ])
+    }
+
+    /// `OP_1 <key>`, with the 32 byte x-only output key (BIP-341).
+    pub fn p2tr(output_key: &XOnlyPublicKey) -> Self {
+        Script::new(vec![
+            Command::Op(Opcode::OP_1),
+            Command::Data(output_key.serialize().to_vec()),
+        ])
+    }
+
+    /// `OP_n <program>`, paying to a witness program of version `n`. Fails outside the BIP-141
+    /// limits segwit addresses are held to.
+    pub fn from_witness_program(version: u8, program: &[u8]) -> Result<Self, crate::Error> {
+        bech32::check_witness_program(version, program)?;
+
+        let version = match version {
+            0 => Opcode::OP_0,
+            version => {
+                Opcode::from_byte(Opcode::OP_1.to_byte() + version - 1).expect("versions up to 16")
+            }
+        };
+
+        Ok(Script::new(vec![
+            Command::Op(version),
+            Command::Data(program.to_vec()),
+        ]))
+    }
+
+    /// The P2WSH script paying to this witness script.
+    pub fn to_p2wsh(&self) -> Script {
+        Script::p2wsh(&hashes::sha256(&self.raw_serialize()))
+    }
+
+    /// The version and program of a segwit output: a small number followed by a direct push
+    /// of 2 to 40 bytes (BIP-141). Pushes with `OP_PUSHDATA*` are not witness programs.
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        let [op, len, program @ ..] = self.raw.as_slice() else {
+            return None;
+        };
+
+        let version = match *op {
+            0x00 => 0,
+            op if (Opcode::OP_1.to_byte()..=Opcode::OP_16.to_byte()).contains(&op) => {
+                op - Opcode::OP_1.to_byte() + 1
+            }
+            _ => return None,
+        };
+
+        ((2..=40).contains(len) && usize::from(*len) == program.len()).then_some((version, program))
+    }
+
+    /// Whether the script only pushes data, as BIP-16 requires of P2SH script_sigs.
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().all(|ins| match ins {
+            Ok((Command::Data(_), _)) => true,
+            Ok((Command::Op(op), _)) => op.to_byte() <= Opcode::OP_16.to_byte(),
+            Err(_) => false,
+        })
+    }
+
+    /// Parses a script prefixed with its length as a varint, as found in transactions.
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, crate::Error> {
+        let len = varint::read(reader)?;
+
+        let mut bytes = Vec::new();
+        reader.take(len).read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 != len {
+            return Err(crate::Error::ValueError(format!(
+                "Expected a script of {len} bytes, found {}",
+                bytes.len()
+            )));
+        }
+
+        // Scripts are only decoded when run, so one that does not decode is kept as it is
+        Ok(Script::from_raw(bytes))
+    }
+
+    /// Parses a script without a length prefix, failing if its commands do not decode.
+    pub fn parse_raw(bytes: &[u8]) -> Result<Self, crate::Error> {
+        let script = Script::from_raw(bytes.to_vec());
+        script.cmds()?;
+
+        Ok(script)
+    }
+
+    /// Serialization without the length prefix: the bytes the script was parsed from.
+    pub fn raw_serialize(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+
+    /// Serialization prefixed with its length as a varint.
+    pub fn serialize(&self) -> Vec<u8> {
+        let raw = self.raw_serialize();
+
+        let mut result = varint::encode(raw.len() as u64);
+        result.extend(raw);
+
+        result
+    }
+}
+
+/// Iterator over the commands of a raw script, see `Script::instructions`. Stops after the
+/// first command that does not decode.
+pub(crate) struct Instructions<'a> {
+    raw: &'a [u8],
+    rest: &'a [u8],
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<(Command, usize), crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&byte, mut rest) = self.rest.split_first()?;
+
+        let len = match byte {
+            len @ 0x01..=0x4b => Ok(usize::from(len)),
+            0x4c => take(&mut rest, 1).map(|len| usize::from(len[0])),
+            0x4d => take(&mut rest, 2).map(|len| usize::from(u16::from_le_bytes([len[0], len[1]]))),
+            0x4e => take(&mut rest, 4)
+                .map(|len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize),
+            byte => {
+                let op = Opcode::from_byte(byte).expect("not a data push");
+                self.rest = rest;
+                return Some(Ok((Command::Op(op), self.raw.len() - rest.len())));
+            }
+        };
+
+        match len.and_then(|len| take(&mut rest, len)) {
+            Ok(data) => {
+                self.rest = rest;
+                Some(Ok((
+                    Command::Data(data.to_vec()),
+                    self.raw.len() - rest.len(),
+                )))
+            }
+            Err(err) => {
+                self.rest = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Splits `len` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], crate::Error> {
+    if bytes.len() < len {
+        return Err(crate::Error::ValueError(format!(
+            "Script push of {len} bytes, but only {} left",
+            bytes.len()
+        )));
+    }
+
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+
+    Ok(head)
+}
+
+impl std::fmt::Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let cmds: Vec<String> = self
+            .instructions()
+            .map(|ins| match ins {
+                Ok((Command::Op(op), _)) => op.name(),
+                Ok((Command::Data(data), _)) => hex::encode(&data),
+                Err(_) => "[error]".to_string(),
+            })
+            .collect();
+
+        write!(f, "{}", cmds.join(" "))
+    }
+}
+
+impl std::ops::Add for Script {
+    type Output = Script;
+
+    fn add(mut self, rhs: Script) -> Script {
+        self.raw.extend(rhs.raw);
+        self
+    }
+}
+
+/// Why a script failed. The opcode it failed at is carried by `crate::Error::Script`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScriptError {
+    /// An opcode needed more stack items than there were.
+    StackUnderflow,
+    /// One of the `*VERIFY` opcodes found a false value.
+    VerifyFailed,
+    OpReturn,
+    /// Disabled opcodes fail the script even in an unexecuted branch.
+    DisabledOpcode,
+    /// A reserved or undefined opcode was executed.
+    BadOpcode,
+    /// `OP_ELSE`/`OP_ENDIF` without `OP_IF`, or `OP_IF` without `OP_ENDIF`.
+    UnbalancedConditional,
+    /// Arithmetic operands are limited to 4 bytes.
+    NumberOverflow,
+    /// A pushed element is larger than 520 bytes.
+    PushSize,
+    /// The stack and alt stack together hold more than 1000 elements.
+    StackSize,
+    /// More than 201 non-push opcodes, counting multisig public keys.
+    OpCount,
+    PubkeyCount,
+    SigCount,
+    /// The script finished with an empty stack or a false value on top.
+    EvalFalse,
+    /// The script_sig of a P2SH spend contains opcodes other than pushes.
+    SigPushOnly,
+    /// A native witness program spent with a script_sig, or a nested one with more than the
+    /// redeem script in it.
+    WitnessMalleated,
+    /// The witness does not satisfy the shape or hash the witness program requires.
+    WitnessProgramMismatch,
+    /// Version 0 witness programs are either 20 or 32 bytes.
+    WitnessProgramWrongLength,
+    /// A witness was given for an output that is not a witness program.
+    WitnessUnexpected,
+    /// Witness scripts must leave exactly one element on the stack.
+    CleanStack,
+    /// Tapscript signatures must be checked against a non-empty public key.
+    PubkeyType,
+    /// A non-empty Schnorr signature is invalid, which fails the script rather than the check.
+    SchnorrSig,
+    /// Tapscripts check at most one signature per 50 witness bytes.
+    TapscriptValidationWeight,
+    /// `OP_CHECKMULTISIG` is replaced by `OP_CHECKSIGADD` in tapscript.
+    TapscriptCheckMultisig,
+    /// The argument of `OP_IF` in tapscript must be empty or exactly 0x01.
+    TapscriptMinimalIf,
+}
+
+const MAX_ELEMENT_SIZE: usize = 520;
+const MAX_STACK_SIZE: usize = 1000;
+const MAX_OPS: usize = 201;
+const MAX_PUBKEYS_PER_MULTISIG: i64 = 20;
+/// Tapscript validation weight spent per signature check, and added to the witness size.
+const VALIDATION_WEIGHT_PER_SIGOP: i64 = 50;
+
+/// Minimal little-endian sign-magnitude encoding of a Script number.
+pub fn encode_num(num: i64) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut abs = num.unsigned_abs();
+
+    while abs > 0 {
+        result.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+
+    if let Some(last) = result.last_mut() {
+        if *last & 0x80 != 0 {
+            result.push(if num < 0 { 0x80 } else { 0x00 });
+        } else if num < 0 {
+            *last |= 0x80;
+        }
+    }
+
+    result
+}
+
+/// Inverse of `encode_num`. Accepts at most 8 bytes, callers enforce tighter limits.
+pub fn decode_num(bytes: &[u8]) -> i64 {
+    let Some((last, _)) = bytes.split_last() else {
+        return 0;
+    };
+
+    let mut result = bytes
+        .iter()
+        .rev()
+        .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+
+    if last & 0x80 != 0 {
+        result &= !(0x80 << (8 * (bytes.len() - 1)));
+        -(result as i64)
+    } else {
+        result as i64
+    }
+}
+
+/// Empty arrays, zeros and negative zero are false, anything else is true.
+fn cast_to_bool(bytes: &[u8]) -> bool {
+    match bytes.split_last() {
+        None => false,
+        Some((last, rest)) => rest.iter().any(|byte| *byte != 0) || (last & 0x7f) != 0,
+    }
+}
+
+pub(crate) fn check_ecdsa(z: U256, sig: &[u8], sec: &[u8]) -> bool {
+    // The last byte of the signature is the sighash type, already accounted for in z
+    let Some((_, der)) = sig.split_last() else {
+        return false;
+    };
+
+    match (Signature::parse_der(der), PublicKey::parse_sec(sec)) {
+        (Ok(sig), Ok(point)) => point.verify(z, &sig),
+        _ => false,
+    }
+}
+
+/// Checks a 64 byte BIP-340 signature of `msg` against an x-only public key.
+pub(crate) fn check_schnorr(msg: &[u8; 32], sig: &[u8], public_key: &[u8]) -> bool {
+    match (
+        SchnorrSignature::parse(sig),
+        XOnlyPublicKey::parse(public_key),
+    ) {
+        (Ok(sig), Ok(public_key)) => public_key.verify(msg, &sig),
+        _ => false,
+    }
+}
+
+/// Which signature hash algorithm signatures commit to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum SigVersion {
+    Base,
+    /// BIP-143
+    WitnessV0,
+    /// BIP-341 key path spends
+    Taproot,
+    /// BIP-342, with the hash of the leaf being run, computed from the script bytes in the
+    /// witness, and the position of the last executed `OP_CODESEPARATOR` (`u32::MAX` if none),
+    /// which signatures commit to.
+    Tapscript {
+        leaf_hash: [u8; 32],
+        codesep_pos: u32,
+    },
+}
+
+/// Checks a signature (including its sighash type byte, if any) against a public key: SEC for
+/// ECDSA, x-only for Schnorr. It is also given the script code legacy and BIP-143 signature
+/// hashes commit to: the executed script from its last executed `OP_CODESEPARATOR` on, without
+/// the signatures being checked for legacy ones.
+pub(crate) type SigChecker<'a> = dyn Fn(SigVersion, &Script, &[u8], &[u8]) -> bool + 'a;
+
+impl Script {
+    /// Runs the script on an empty stack, checking signatures against `z`. Succeeds if it ends
+    /// with a true value on top of the stack. As `z` is fixed, scripts with `OP_CODESEPARATOR`
+    /// should be verified through `Tx::verify_input` instead.
+    pub fn evaluate(&self, z: U256) -> Result<(), crate::Error> {
+        let mut stack = Vec::new();
+        self.execute(&mut stack, SigVersion::Base, &|_, _, sig, sec| {
+            check_ecdsa(z, sig, sec)
+        })?;
+
+        check_result(&stack)
+    }
+
+    /// Validates a spend: runs `script_sig`, then `script_pubkey` on the stack it leaves. If
+    /// `script_pubkey` is P2SH, the redeem script on top of that stack is run as well (BIP-16).
+    /// Witness programs, native or nested in P2SH, are checked against `witness` (BIP-141).
+    pub(crate) fn verify_spend(
+        script_sig: &Script,
+        script_pubkey: &Script,
+        witness: &[Vec<u8>],
+        check_sig: &SigChecker,
+    ) -> Result<(), crate::Error> {
+        let fail = |error| {
+            Err(crate::Error::Script {
+                opcode: None,
+                error,
+            })
+        };
+
+        let mut stack = Vec::new();
+        script_sig.execute(&mut stack, SigVersion::Base, check_sig)?;
+        let mut redeem_stack = stack.clone();
+
+        script_pubkey.execute(&mut stack, SigVersion::Base, check_sig)?;
+        check_result(&stack)?;
+
+        let mut witness_checked = false;
+
+        if let Some((version, program)) = script_pubkey.witness_program() {
+            if !script_sig.raw.is_empty() {
+                return fail(ScriptError::WitnessMalleated);
+            }
+            verify_witness(version, program, witness, false, check_sig)?;
+            witness_checked = true;
+        } else if script_pubkey.p2sh_hash().is_some() {
+            if !script_sig.is_push_only() {
+                return fail(ScriptError::SigPushOnly);
+            }
+
+            // Not empty, the script_pubkey hashed it
+            let redeem_script = Script::from_raw(redeem_stack.pop().expect("redeem script"));
+
+            if let Some((version, program)) = redeem_script.witness_program() {
+                // The script_sig must be the push of the redeem script and nothing else
+                if *script_sig != Script::new(vec![Command::Data(redeem_script.raw.clone())]) {
+                    return fail(ScriptError::WitnessMalleated);
+                }
+                verify_witness(version, program, witness, true, check_sig)?;
+                witness_checked = true;
+            } else {
+                redeem_script.execute(&mut redeem_stack, SigVersion::Base, check_sig)?;
+                check_result(&redeem_stack)?;
+            }
+        }
+
+        if !witness_checked && !witness.is_empty() {
+            return fail(ScriptError::WitnessUnexpected);
+        }
+
+        Ok(())
+    }
+
+    /// Runs the script on `stack`, delegating signature checks to `check_sig`.
+    pub(crate) fn execute(
+        &self,
+        stack: &mut Vec<Vec<u8>>,
+        sig_version: SigVersion,
+        check_sig: &SigChecker,
+    ) -> Result<(), crate::Error> {
+        self.execute_with_budget(stack, sig_version, 0, check_sig)
+    }
+
+    /// Like `execute`, with the validation weight tapscript signature checks draw from.
+    fn execute_with_budget(
+        &self,
+        stack: &mut Vec<Vec<u8>>,
+        sig_version: SigVersion,
+        validation_weight: i64,
+        check_sig: &SigChecker,
+    ) -> Result<(), crate::Error> {
+        let mut interpreter = Interpreter {
+            script: self,
+            sig_version,
+            stack: std::mem::take(stack),
+            alt_stack: Vec::new(),
+            exec: Vec::new(),
+            op_count: 0,
+            position: 0,
+            code_start: 0,
+            end_offset: 0,
+            validation_weight,
+            check_sig,
+        };
+
+        for (position, ins) in self.instructions().enumerate() {
+            interpreter.position = position as u32;
+            let (cmd, end_offset) = ins.map_err(|_| crate::Error::Script {
+                opcode: None,
+                error: ScriptError::BadOpcode,
+            })?;
+            interpreter.end_offset = end_offset;
+            let (opcode, result) = match cmd {
+                Command::Data(data) => (None, interpreter.push_data(&data)),
+                Command::Op(op) => (Some(op), interpreter.step(op)),
+            };
+
+            result.map_err(|error| crate::Error::Script { opcode, error })?;
+
+            if interpreter.stack.len() + interpreter.alt_stack.len() > MAX_STACK_SIZE {
+                return Err(crate::Error::Script {
+                    opcode,
+                    error: ScriptError::StackSize,
+                });
+            }
+        }
+
+        if !interpreter.exec.is_empty() {
+            return Err(crate::Error::Script {
+                opcode: None,
+                error: ScriptError::UnbalancedConditional,
+            });
+        }
+
+        *stack = interpreter.stack;
+
+        Ok(())
+    }
+}
+
+/// Runs a segwit program of the given version against its witness. Unknown versions, and
+/// taproot nested in P2SH, are left for future soft forks and always succeed.
+fn verify_witness(
+    version: u8,
+    program: &[u8],
+    witness: &[Vec<u8>],
+    is_p2sh: bool,
+    check_sig: &SigChecker,
+) -> Result<(), crate::Error> {
+    let fail = |error| {
+        Err(crate::Error::Script {
+            opcode: None,
+            error,
+        })
+    };
+
+    let (script, mut stack) = match (version, program.len()) {
+        // P2WPKH: the witness is a signature and a public key
+        (0, 20) => {
+            if witness.len() != 2 {
+                return fail(ScriptError::WitnessProgramMismatch);
+            }
+            let hash = program.try_into().expect("20 bytes");
+            (Script::p2pkh(hash), witness.to_vec())
+        }
+        // P2WSH: the witness script is the last element of the witness
+        (0, 32) => {
+            let Some((witness_script, items)) = witness.split_last() else {
+                return fail(ScriptError::WitnessProgramMismatch);
+            };
+            if hashes::sha256(witness_script) != program {
+                return fail(ScriptError::WitnessProgramMismatch);
+            }
+            (Script::from_raw(witness_script.clone()), items.to_vec())
+        }
+        (0, _) => return fail(ScriptError::WitnessProgramWrongLength),
+        (1, 32) if !is_p2sh => return verify_taproot(program, witness, check_sig),
+        _ => return Ok(()),
+    };
+
+    if stack.iter().any(|item| item.len() > MAX_ELEMENT_SIZE) {
+        return fail(ScriptError::PushSize);
+    }
+
+    script.execute(&mut stack, SigVersion::WitnessV0, check_sig)?;
+
+    if stack.len() != 1 {
+        return fail(ScriptError::CleanStack);
+    }
+
+    check_result(&stack)
+}
+
+/// Spends a taproot output (BIP-341): with a signature for the output key, or by revealing a
+/// script committed to by it, its control block and the inputs of the script.
+fn verify_taproot(
+    program: &[u8],
+    witness: &[Vec<u8>],
+    check_sig: &SigChecker,
+) -> Result<(), crate::Error> {
+    let fail = |error| {
+        Err(crate::Error::Script {
+            opcode: None,
+            error,
+        })
+    };
+
+    let (stack, _) = taproot::split_annex(witness);
+
+    let (script, control_block, items) = match stack {
+        [] => return fail(ScriptError::WitnessProgramMismatch),
+        // Key path: a signature for the output key itself
+        [sig] => {
+            if !check_sig(SigVersion::Taproot, &Script::default(), sig, program) {
+                return fail(ScriptError::SchnorrSig);
+            }
+            return Ok(());
+        }
+        [items @ .., script, control_block] => (script, control_block, items),
+    };
+
+    let committed = match (
+        ControlBlock::parse(control_block),
+        XOnlyPublicKey::parse(program),
+    ) {
+        (Ok(control_block), Ok(output_key)) => control_block
+            .verify(&output_key, script)
+            .then_some(control_block),
+        _ => None,
+    };
+    let Some(control_block) = committed else {
+        return fail(ScriptError::WitnessProgramMismatch);
+    };
+
+    // Other leaf versions are left for future soft forks
+    if control_block.leaf_version != TAPSCRIPT_LEAF_VERSION || has_op_success(script)? {
+        return Ok(());
+    }
+
+    if items.iter().any(|item| item.len() > MAX_ELEMENT_SIZE) {
+        return fail(ScriptError::PushSize);
+    }
+    if items.len() > MAX_STACK_SIZE {
+        return fail(ScriptError::StackSize);
+    }
+
+    let witness_size = varint::encode(witness.len() as u64).len()
+        + witness
+            .iter()
+            .map(|item| varint::encode(item.len() as u64).len() + item.len())
+            .sum::<usize>();
+    let validation_weight = VALIDATION_WEIGHT_PER_SIGOP + witness_size as i64;
+
+    let mut stack = items.to_vec();
+    Script::from_raw(script.clone()).execute_with_budget(
+        &mut stack,
+        SigVersion::Tapscript {
+            leaf_hash: taproot::tap_leaf_hash(control_block.leaf_version, script),
+            codesep_pos: u32::MAX,
+        },
+        validation_weight,
+        check_sig,
+    )?;
+
+    if stack.len() != 1 {
+        return fail(ScriptError::CleanStack);
+    }
+
+    check_result(&stack)
+}
+
+/// Whether a raw tapscript contains an `OP_SUCCESSx` opcode, which makes it succeed without
+/// running (BIP-342). The opcodes before it must still decode.
+fn has_op_success(mut script: &[u8]) -> Result<bool, crate::Error> {
+    while let Some((&byte, rest)) = script.split_first() {
+        script = rest;
+
+        let len = match byte {
+            0x01..=0x4b => usize::from(byte),
+            0x4c => usize::from(take(&mut script, 1)?[0]),
+            0x4d => usize::from(u16::from_le_bytes(
+                take(&mut script, 2)?.try_into().expect("2 bytes"),
+            )),
+            0x4e => u32::from_le_bytes(take(&mut script, 4)?.try_into().expect("4 bytes")) as usize,
+            0x50
+            | 0x62
+            | 0x7e..=0x81
+            | 0x83..=0x86
+            | 0x89..=0x8a
+            | 0x8d..=0x8e
+            | 0x95..=0x99
+            | 0xbb..=0xfe => return Ok(true),
+            _ => 0,
+        };
+        take(&mut script, len)?;
+    }
+
+    Ok(false)
+}
+
+/// A script succeeds if it leaves a true value on top of the stack.
+fn check_result(stack: &[Vec<u8>]) -> Result<(), crate::Error> {
+    match stack.last() {
+        Some(top) if cast_to_bool(top) => Ok(()),
+        _ => Err(crate::Error::Script {
+            opcode: None,
+            error: ScriptError::EvalFalse,
+        }),
+    }
+}
+
+struct Interpreter<'a> {
+    script: &'a Script,
+    sig_version: SigVersion,
+    stack: Vec<Vec<u8>>,
+    alt_stack: Vec<Vec<u8>>,
+    /// One entry per open `OP_IF`, telling whether its current branch runs.
+    exec: Vec<bool>,
+    op_count: usize,
+    /// Index of the command being run, which `OP_CODESEPARATOR` records in tapscript.
+    position: u32,
+    /// Offset of the script code, just after the last executed `OP_CODESEPARATOR`.
+    code_start: usize,
+    /// Offset just after the command being run.
+    end_offset: usize,
+    /// What is left of the tapscript signature budget.
+    validation_weight: i64,
+    check_sig: &'a SigChecker<'a>,
+}
+
+impl Interpreter<'_> {
+    fn executing(&self) -> bool {
+        self.exec.iter().all(|branch| *branch)
+    }
+
+    fn push_data(&mut self, data: &[u8]) -> Result<(), ScriptError> {
+        if data.len() > MAX_ELEMENT_SIZE {
+            return Err(ScriptError::PushSize);
+        }
+        if self.executing() {
+            self.stack.push(data.to_vec());
+        }
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Vec<u8>, ScriptError> {
+        self.stack.pop().ok_or(ScriptError::StackUnderflow)
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, ScriptError> {
+        Ok(cast_to_bool(&self.pop()?))
+    }
+
+    fn pop_num(&mut self) -> Result<i64, ScriptError> {
+        let bytes = self.pop()?;
+        if bytes.len() > 4 {
+            return Err(ScriptError::NumberOverflow);
+        }
+
+        Ok(decode_num(&bytes))
+    }
+
+    fn push_num(&mut self, num: i64) {
+        self.stack.push(encode_num(num));
+    }
+
+    fn push_bool(&mut self, value: bool) {
+        self.push_num(i64::from(value));
+    }
+
+    /// Fails unless the stack holds at least `n` items.
+    fn require(&self, n: usize) -> Result<(), ScriptError> {
+        if self.stack.len() < n {
+            return Err(ScriptError::StackUnderflow);
+        }
+
+        Ok(())
+    }
+
+    /// Index of the item `depth` positions below the top, which is at depth 0.
+    fn index(&self, depth: i64) -> Result<usize, ScriptError> {
+        usize::try_from(depth)
+            .ok()
+            .filter(|depth| *depth < self.stack.len())
+            .map(|depth| self.stack.len() - 1 - depth)
+            .ok_or(ScriptError::StackUnderflow)
+    }
+
+    fn verify(&mut self) -> Result<(), ScriptError> {
+        if !self.pop_bool()? {
+            return Err(ScriptError::VerifyFailed);
+        }
+
+        Ok(())
+    }
+
+    /// The script from the last executed `OP_CODESEPARATOR` on. Legacy signatures are also
+    /// removed from it (FindAndDelete).
+    fn script_code(&self, sigs: &[&[u8]]) -> Script {
+        let script_code = Script::from_raw(self.script.raw[self.code_start..].to_vec());
+        if self.sig_version != SigVersion::Base {
+            return script_code;
+        }
+
+        sigs.iter().fold(script_code, |script_code, sig| {
+            script_code.find_and_delete(&Script::new(vec![Command::Data(sig.to_vec())]))
+        })
+    }
+
+    fn is_tapscript(&self) -> bool {
+        matches!(self.sig_version, SigVersion::Tapscript { .. })
+    }
+
+    /// Tapscript signature check (BIP-342): an empty signature fails the check, an invalid
+    /// non-empty one fails the script. Public keys of unknown types, other than 32 bytes, are
+    /// left for future soft forks and accept any non-empty signature.
+    fn check_schnorr_sig(&mut self, sig: &[u8], public_key: &[u8]) -> Result<bool, ScriptError> {
+        let success = !sig.is_empty();
+
+        if success {
+            self.validation_weight -= VALIDATION_WEIGHT_PER_SIGOP;
+            if self.validation_weight < 0 {
+                return Err(ScriptError::TapscriptValidationWeight);
+            }
+        }
+
+        if public_key.is_empty() {
+            return Err(ScriptError::PubkeyType);
+        }
+        if public_key.len() == 32
+            && success
+            && !(self.check_sig)(self.sig_version, self.script, sig, public_key)
+        {
+            return Err(ScriptError::SchnorrSig);
+        }
+
+        Ok(success)
+    }
+
+    fn step(&mut self, op: Opcode) -> Result<(), ScriptError> {
+        use Opcode::*;
+
+        if op.to_byte() > OP_16.to_byte() && !self.is_tapscript() {
+            self.op_count += 1;
+            if self.op_count > MAX_OPS {
+                return Err(ScriptError::OpCount);
+            }
+        }
+
+        match op {
+            OP_CAT | OP_SUBSTR | OP_LEFT | OP_RIGHT | OP_INVERT | OP_AND | OP_OR | OP_XOR
+            | OP_2MUL | OP_2DIV | OP_MUL | OP_DIV | OP_MOD | OP_LSHIFT | OP_RSHIFT => {
+                return Err(ScriptError::DisabledOpcode);
+            }
+            OP_VERIF | OP_VERNOTIF => return Err(ScriptError::BadOpcode),
+            _ => {}
+        }
+
+        match op {
+            OP_IF | OP_NOTIF => {
+                let branch = if self.executing() {
+                    let top = self.pop()?;
+                    if self.is_tapscript() && !(top.is_empty() || top == [1]) {
+                        return Err(ScriptError::TapscriptMinimalIf);
+                    }
+                    cast_to_bool(&top) == (op == OP_IF)
+                } else {
+                    false
+                };
+                self.exec.push(branch);
+                return Ok(());
+            }
+            OP_ELSE => {
+                let branch = self
+                    .exec
+                    .last_mut()
+                    .ok_or(ScriptError::UnbalancedConditional)?;
+                *branch = !*branch;
+                return Ok(());
+            }
+            OP_ENDIF => {
+                self.exec.pop().ok_or(ScriptError::UnbalancedConditional)?;
+                return Ok(());
+            }
+            _ if !self.executing() => return Ok(()),
+            _ => {}
+        }
+
+        match op {
+            OP_0 => self.stack.push(Vec::new()),
+            OP_1NEGATE => self.push_num(-1),
+            OP_1 | OP_2 | OP_3 | OP_4 | OP_5 | OP_6 | OP_7 | OP_8 | OP_9 | OP_10 | OP_11
+            | OP_12 | OP_13 | OP_14 | OP_15 | OP_16 => {
+                self.push_num(i64::from(op.to_byte() - OP_1.to_byte() + 1))
+            }
+
+            // Locktime checks need the spending transaction, so they act as their NOP ancestors
+            OP_NOP
+            | OP_NOP1
+            | OP_CHECKLOCKTIMEVERIFY
+            | OP_CHECKSEQUENCEVERIFY
+            | OP_NOP4
+            | OP_NOP5
+            | OP_NOP6
+            | OP_NOP7
+            | OP_NOP8
+            | OP_NOP9
+            | OP_NOP10 => {}
+            // Legacy and BIP-143 signatures commit to the script after the last executed
+            // OP_CODESEPARATOR, tapscript ones to its position
+            OP_CODESEPARATOR => {
+                let position = self.position;
+                match &mut self.sig_version {
+                    SigVersion::Tapscript { codesep_pos, .. } => *codesep_pos = position,
+                    _ => self.code_start = self.end_offset,
+                }
+            }
+
+            OP_VERIFY => self.verify()?,
+            OP_RETURN => return Err(ScriptError::OpReturn),
+
+            OP_TOALTSTACK => {
+                let item = self.pop()?;
+                self.alt_stack.push(item);
+            }
+            OP_FROMALTSTACK => {
+                let item = self.alt_stack.pop().ok_or(ScriptError::StackUnderflow)?;
+                self.stack.push(item);
+            }
+            OP_2DROP => {
+                self.require(2)?;
+                self.stack.truncate(self.stack.len() - 2);
+            }
+            OP_2DUP | OP_3DUP | OP_2OVER => {
+                let (count, depth) = match op {
+                    OP_2DUP => (2, 2),
+                    OP_3DUP => (3, 3),
+                    _ => (2, 4),
+                };
+                self.require(depth)?;
+                let start = self.stack.len() - depth;
+                self.stack.extend_from_within(start..start + count);
+            }
+            OP_2ROT => {
+                self.require(6)?;
+                let start = self.stack.len() - 6;
+                self.stack[start..].rotate_left(2);
+            }
+            OP_2SWAP => {
+                self.require(4)?;
+                let start = self.stack.len() - 4;
+                self.stack[start..].rotate_left(2);
+            }
+            OP_IFDUP => {
+                let top = self.stack.last().ok_or(ScriptError::StackUnderflow)?;
+                if cast_to_bool(top) {
+                    self.stack.push(top.clone());
+                }
+            }
+            OP_DEPTH => self.push_num(self.stack.len() as i64),
+            OP_DROP => {
+                self.pop()?;
+            }
+            OP_DUP => {
+                let top = self.stack.last().ok_or(ScriptError::StackUnderflow)?;
+                self.stack.push(top.clone());
+            }
+            OP_NIP => {
+                let index = self.index(1)?;
+                self.stack.remove(index);
+            }
+            OP_OVER => {
+                let index = self.index(1)?;
+                self.stack.push(self.stack[index].clone());
+            }
+            OP_PICK | OP_ROLL => {
+                let depth = self.pop_num()?;
+                let index = self.index(depth)?;
+                let item = if op == OP_PICK {
+                    self.stack[index].clone()
+                } else {
+                    self.stack.remove(index)
+                };
+                self.stack.push(item);
+            }
+            OP_ROT => {
+                let index = self.index(2)?;
+                let item = self.stack.remove(index);
+                self.stack.push(item);
+            }
+            OP_SWAP => {
+                let index = self.index(1)?;
+                self.stack.swap(index, index + 1);
+            }
+            OP_TUCK => {
+                let index = self.index(1)?;
+                let top = self.stack[index + 1].clone();
+                self.stack.insert(index, top);
+            }
+            OP_SIZE => {
+                let top = self.stack.last().ok_or(ScriptError::StackUnderflow)?;
+                self.push_num(top.len() as i64);
+            }
+
+            OP_EQUAL | OP_EQUALVERIFY => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push_bool(a == b);
+                if op == OP_EQUALVERIFY {
+                    self.verify()?;
+                }
+            }
+
+            OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => {
+                let a = self.pop_num()?;
+                let result = match op {
+                    OP_1ADD => a + 1,
+                    OP_1SUB => a - 1,
+                    OP_NEGATE => -a,
+                    OP_ABS => a.abs(),
+                    OP_NOT => i64::from(a == 0),
+                    _ => i64::from(a != 0),
+                };
+                self.push_num(result);
+            }
+            OP_ADD
+            | OP_SUB
+            | OP_BOOLAND
+            | OP_BOOLOR
+            | OP_NUMEQUAL
+            | OP_NUMEQUALVERIFY
+            | OP_NUMNOTEQUAL
+            | OP_LESSTHAN
+            | OP_GREATERTHAN
+            | OP_LESSTHANOREQUAL
+            | OP_GREATERTHANOREQUAL
+            | OP_MIN
+            | OP_MAX => {
+                let b = self.pop_num()?;
+                let a = self.pop_num()?;
+                let result = match op {
+                    OP_ADD => a + b,
+                    OP_SUB => a - b,
+                    OP_BOOLAND => i64::from(a != 0 && b != 0),
+                    OP_BOOLOR => i64::from(a != 0 || b != 0),
+                    OP_NUMEQUAL | OP_NUMEQUALVERIFY => i64::from(a == b),
+                    OP_NUMNOTEQUAL => i64::from(a != b),
+                    OP_LESSTHAN => i64::from(a < b),
+                    OP_GREATERTHAN => i64::from(a > b),
+                    OP_LESSTHANOREQUAL => i64::from(a <= b),
+                    OP_GREATERTHANOREQUAL => i64::from(a >= b),
+                    OP_MIN => a.min(b),
+                    _ => a.max(b),
+                };
+                self.push_num(result);
+                if op == OP_NUMEQUALVERIFY {
+                    self.verify()?;
+                }
+            }
+            OP_WITHIN => {
+                let max = self.pop_num()?;
+                let min = self.pop_num()?;
+                let x = self.pop_num()?;
+                self.push_bool(min <= x && x < max);
+            }
+
+            OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => {
+                let item = self.pop()?;
+                let digest = match op {
+                    OP_RIPEMD160 => hashes::ripemd160(&item).to_vec(),
+                    OP_SHA1 => hashes::sha1(&item).to_vec(),
+                    OP_SHA256 => hashes::sha256(&item).to_vec(),
+                    OP_HASH160 => hashes::hash160(&item).to_vec(),
+                    _ => hashes::hash256(&item).to_vec(),
+                };
+                self.stack.push(digest);
+            }
+
+            OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                let public_key = self.pop()?;
+                let sig = self.pop()?;
+                let valid = if self.is_tapscript() {
+                    self.check_schnorr_sig(&sig, &public_key)?
+                } else {
+                    let script_code = self.script_code(&[&sig]);
+                    (self.check_sig)(self.sig_version, &script_code, &sig, &public_key)
+                };
+                self.push_bool(valid);
+                if op == OP_CHECKSIGVERIFY {
+                    self.verify()?;
+                }
+            }
+            OP_CHECKSIGADD if self.is_tapscript() => {
+                self.require(3)?;
+                let public_key = self.pop()?;
+                let n = self.pop_num()?;
+                let sig = self.pop()?;
+                let valid = self.check_schnorr_sig(&sig, &public_key)?;
+                self.push_num(n + i64::from(valid));
+            }
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY if self.is_tapscript() => {
+                return Err(ScriptError::TapscriptCheckMultisig);
+            }
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                let valid = self.check_multisig()?;
+                self.push_bool(valid);
+                if op == OP_CHECKMULTISIGVERIFY {
+                    self.verify()?;
+                }
+            }
+
+            _ => return Err(ScriptError::BadOpcode),
+        }
+
+        Ok(())
+    }
+
+    fn check_multisig(&mut self) -> Result<bool, ScriptError> {
+        let n = self.pop_num()?;
+        if !(0..=MAX_PUBKEYS_PER_MULTISIG).contains(&n) {
+            return Err(ScriptError::PubkeyCount);
+        }
+        self.op_count += n as usize;
+        if self.op_count > MAX_OPS {
+            return Err(ScriptError::OpCount);
+        }
+        self.require(n as usize)?;
+        let secs = self.stack.split_off(self.stack.len() - n as usize);
+
+        let m = self.pop_num()?;
+        if !(0..=n).contains(&m) {
+            return Err(ScriptError::SigCount);
+        }
+        self.require(m as usize)?;
+        let sigs = self.stack.split_off(self.stack.len() - m as usize);
+
+        // An off-by-one in the original implementation pops one extra element
+        self.pop()?;
+
+        let script_code = self.script_code(&sigs.iter().map(Vec::as_slice).collect::<Vec<_>>());
+
+        // Signatures must appear in the same order as their public keys
+        let mut secs = secs.iter();
+        for sig in &sigs {
+            if !secs.any(|sec| (self.check_sig)(self.sig_version, &script_code, sig, sec)) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
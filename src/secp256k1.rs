@@ -0,0 +1,234 @@
+use crypto_bigint::U256;
+
+use crate::{
+    base58,
+    field_element2::FieldElement,
+    hashes,
+    point::{Curve, Point},
+    signature::Signature,
+    Pow,
+};
+
+/// The prime of the field secp256k1 is defined over: 2^256 - 2^32 - 977.
+pub const P: U256 =
+    U256::from_be_hex("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F");
+
+/// The order of the group generated by `G`.
+pub const N: U256 =
+    U256::from_be_hex("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+
+const GX: U256 =
+    U256::from_be_hex("79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798");
+const GY: U256 =
+    U256::from_be_hex("483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8");
+
+/// The generator point.
+pub const G: Point<Secp256k1> = Point::Real(S256Field::new(GX), S256Field::new(GY));
+
+/// A public key is a point on the curve.
+pub type PublicKey = Point<Secp256k1>;
+
+/// Reduces `num` modulo `N`, so it can be used in scalar arithmetic.
+pub(crate) fn scalar(num: U256) -> FieldElement {
+    let order = crypto_bigint::NonZero::new(N).unwrap();
+
+    FieldElement::new_const(num.rem_vartime(&order), N)
+}
+
+/// A `FieldElement` whose prime is always `P`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct S256Field(FieldElement);
+
+impl S256Field {
+    pub const fn new(num: U256) -> Self {
+        S256Field(FieldElement::new_const(num, P))
+    }
+
+    pub fn sqrt(self) -> Option<S256Field> {
+        self.0.sqrt().map(S256Field)
+    }
+}
+
+impl std::ops::Deref for S256Field {
+    type Target = FieldElement;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<i128> for S256Field {
+    fn from(value: i128) -> Self {
+        if value < 0 {
+            panic!("from a negative value")
+        }
+
+        S256Field::new(U256::from_u128(value as u128))
+    }
+}
+
+impl std::ops::Add for S256Field {
+    type Output = S256Field;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        S256Field(self.0.add(rhs.0).expect("same prime"))
+    }
+}
+
+impl std::ops::Sub for S256Field {
+    type Output = S256Field;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        S256Field(self.0.sub(rhs.0).expect("same prime"))
+    }
+}
+
+impl std::ops::Mul for S256Field {
+    type Output = S256Field;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        S256Field(self.0.mul(rhs.0).expect("same prime"))
+    }
+}
+
+impl std::ops::Div for S256Field {
+    type Output = S256Field;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        S256Field(self.0.div(rhs.0).expect("same prime"))
+    }
+}
+
+impl crate::Pow for S256Field {
+    type Output = S256Field;
+
+    fn pow(self, exponent: i32) -> Self::Output {
+        S256Field(self.0.pow(exponent))
+    }
+}
+
+impl std::fmt::Display for S256Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0.num())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Secp256k1;
+impl Curve for Secp256k1 {
+    type Scalar = S256Field;
+
+    const A: Self::Scalar = S256Field::new(U256::ZERO);
+    const B: Self::Scalar = S256Field::new(U256::from_u8(7));
+}
+
+impl Point<Secp256k1> {
+    pub fn verify(&self, z: U256, sig: &Signature) -> bool {
+        if *self == Point::Infinity {
+            return false;
+        }
+
+        if sig.r == U256::ZERO || sig.r >= N || sig.s == U256::ZERO || sig.s >= N {
+            return false;
+        }
+
+        let s_inv = scalar(sig.s).inverse().expect("s is not zero");
+        let u = scalar(z).mul(s_inv).expect("same prime");
+        let v = scalar(sig.r).mul(s_inv).expect("same prime");
+
+        let total = G.scalar_mul_u256(u.num()) + self.scalar_mul_u256(v.num());
+
+        match total {
+            Point::Real(x, _) => scalar(x.num()).num() == sig.r,
+            Point::Infinity => false,
+        }
+    }
+
+    /// Like `verify`, but also rejects high-S signatures.
+    pub fn verify_strict(&self, z: U256, sig: &Signature) -> bool {
+        sig.is_low_s() && self.verify(z, sig)
+    }
+}
+
+impl Point<Secp256k1> {
+    /// SEC serialization. Compressed keys are 33 bytes long, uncompressed keys 65 bytes long.
+    pub fn sec(&self, compressed: bool) -> Vec<u8> {
+        let Point::Real(x, y) = self else {
+            return vec![0x00];
+        };
+
+        let mut result = Vec::with_capacity(65);
+
+        if compressed {
+            let prefix = if y.num().bit_vartime(0) { 0x03 } else { 0x02 };
+            result.push(prefix);
+            result.extend_from_slice(&x.num().to_be_bytes());
+        } else {
+            result.push(0x04);
+            result.extend_from_slice(&x.num().to_be_bytes());
+            result.extend_from_slice(&y.num().to_be_bytes());
+        }
+
+        result
+    }
+
+    /// Whether y is even. False for the point at infinity.
+    pub fn has_even_y(&self) -> bool {
+        match self {
+            Point::Real(_, y) => !y.num().bit_vartime(0),
+            Point::Infinity => false,
+        }
+    }
+
+    pub fn hash160(&self, compressed: bool) -> [u8; 20] {
+        hashes::hash160(&self.sec(compressed))
+    }
+
+    /// Pay-to-pubkey-hash address of the point.
+    pub fn address(&self, compressed: bool, network: crate::Network) -> String {
+        let mut payload = vec![network.p2pkh_prefix()];
+        payload.extend_from_slice(&self.hash160(compressed));
+
+        base58::encode_check(&payload)
+    }
+
+    pub fn parse_sec(sec: &[u8]) -> Result<Self, crate::Error> {
+        let coordinate = |bytes: &[u8]| {
+            let num = U256::from_be_slice(bytes);
+            FieldElement::new(num, P).map(S256Field)
+        };
+
+        match (sec.first(), sec.len()) {
+            (Some(0x04), 65) => {
+                let x = coordinate(&sec[1..33])?;
+                let y = coordinate(&sec[33..65])?;
+
+                Point::new(Some((x, y)))
+            }
+            (Some(prefix @ (0x02 | 0x03)), 33) => {
+                let x = coordinate(&sec[1..33])?;
+
+                let alpha = x.pow(3) + Secp256k1::B;
+                let beta = alpha.sqrt().ok_or_else(|| {
+                    crate::Error::ValueError(format!("No point on the curve with x = {x}"))
+                })?;
+
+                let is_odd = *prefix == 0x03;
+                let y = if beta.num().bit_vartime(0) == is_odd {
+                    beta
+                } else {
+                    S256Field::new(P - beta.num())
+                };
+
+                Ok(Point::Real(x, y))
+            }
+            (Some(prefix @ 0x02..=0x04), len) => Err(crate::Error::ValueError(format!(
+                "Invalid SEC length {len} for prefix {prefix:#04x}"
+            ))),
+            (Some(prefix), _) => Err(crate::Error::ValueError(format!(
+                "Invalid SEC prefix {prefix:#04x}"
+            ))),
+            (None, _) => Err(crate::Error::ValueError("Empty SEC".to_string())),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crypto_bigint::U256;
+
+use crate::secp256k1::N;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Signature {
+    pub r: U256,
+    pub s: U256,
+}
+
+impl Signature {
+    pub fn new(r: U256, s: U256) -> Self {
+        Signature { r, s }
+    }
+
+    /// Whether `s` is at most `N / 2`, as required by Bitcoin Core's standardness rules
+    /// (BIP-62).
+    pub fn is_low_s(&self) -> bool {
+        self.s <= N.shr_vartime(1)
+    }
+
+    /// Whether `r` fits in 32 bytes of DER without a padding byte, which keeps the DER
+    /// signature at most 71 bytes long.
+    pub fn is_low_r(&self) -> bool {
+        !self.r.bit_vartime(255)
+    }
+
+    /// Both `(r, s)` and `(r, N - s)` are valid signatures of the same message. This returns
+    /// the low-S one.
+    pub fn normalize(&self) -> Signature {
+        if self.is_low_s() {
+            return *self;
+        }
+
+        Signature::new(self.r, N - self.s)
+    }
+
+    /// DER serialization: `0x30 len 0x02 len(r) r 0x02 len(s) s`.
+    pub fn der(&self) -> Vec<u8> {
+        let r = der_integer(self.r);
+        let s = der_integer(self.s);
+
+        let mut result = vec![0x30, (r.len() + s.len()) as u8];
+        result.extend(r);
+        result.extend(s);
+
+        result
+    }
+
+    /// Parses a DER signature, enforcing the strict encoding rules of BIP-66. The sighash byte
+    /// must not be included.
+    pub fn parse_der(der: &[u8]) -> Result<Signature, crate::Error> {
+        let invalid = |reason: &str| crate::Error::InvalidDer(reason.to_string());
+
+        if der.len() < 8 || der.len() > 72 {
+            return Err(invalid("signature length out of range"));
+        }
+        if der[0] != 0x30 {
+            return Err(invalid("missing compound marker"));
+        }
+        if usize::from(der[1]) != der.len() - 2 {
+            return Err(invalid("length does not match the signature"));
+        }
+
+        let (r, rest) = parse_der_integer(&der[2..])?;
+        let (s, rest) = parse_der_integer(rest)?;
+
+        if !rest.is_empty() {
+            return Err(invalid("trailing bytes after s"));
+        }
+
+        Ok(Signature::new(r, s))
+    }
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Signature({:x},{:x})", self.r, self.s)
+    }
+}
+
+fn der_integer(num: U256) -> Vec<u8> {
+    let bytes = num.to_be_bytes();
+    let first_non_zero = bytes.iter().position(|byte| *byte != 0).unwrap_or(31);
+    let bytes = &bytes[first_non_zero..];
+
+    let mut result = vec![0x02];
+    if bytes[0] & 0x80 != 0 {
+        result.push((bytes.len() + 1) as u8);
+        result.push(0x00);
+    } else {
+        result.push(bytes.len() as u8);
+    }
+    result.extend_from_slice(bytes);
+
+    result
+}
+
+/// Parses `0x02 len value` and returns the value and the remaining bytes.
+fn parse_der_integer(der: &[u8]) -> Result<(U256, &[u8]), crate::Error> {
+    let invalid = |reason: &str| crate::Error::InvalidDer(reason.to_string());
+    let non_canonical = |reason: &str| crate::Error::NonCanonicalDer(reason.to_string());
+
+    let [marker, len, rest @ ..] = der else {
+        return Err(invalid("truncated integer"));
+    };
+    if *marker != 0x02 {
+        return Err(invalid("missing integer marker"));
+    }
+
+    let len = usize::from(*len);
+    if len == 0 {
+        return Err(invalid("zero length integer"));
+    }
+    if len > rest.len() {
+        return Err(invalid("integer length exceeds the signature"));
+    }
+
+    let (value, rest) = rest.split_at(len);
+
+    if value[0] & 0x80 != 0 {
+        return Err(non_canonical("negative integer"));
+    }
+    if len > 1 && value[0] == 0x00 && value[1] & 0x80 == 0 {
+        return Err(non_canonical("excess zero padding"));
+    }
+
+    let value = if value[0] == 0x00 { &value[1..] } else { value };
+    if value.len() > 32 {
+        return Err(invalid("integer larger than 256 bits"));
+    }
+
+    let mut padded = [0u8; 32];
+    padded[32 - value.len()..].copy_from_slice(value);
+
+    Ok((U256::from_be_slice(&padded), rest))
+}
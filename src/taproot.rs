@@ -0,0 +1,230 @@
+use crypto_bigint::U256;
+
+use crate::{
+    hashes,
+    private_key::PrivateKey,
+    schnorr::XOnlyPublicKey,
+    script::Script,
+    secp256k1::{self, G, N},
+    varint,
+};
+
+/// Leaf version of scripts run under the BIP-342 rules.
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+
+/// Leaf versions are even, the low bit of the first control block byte is the output key parity.
+const LEAF_VERSION_MASK: u8 = 0xfe;
+
+/// First byte of the annex, an optional last witness element reserved for future use.
+const ANNEX_TAG: u8 = 0x50;
+
+/// Control blocks hold at most 128 hashes, the maximum depth of a script tree.
+const MAX_MERKLE_PATH: usize = 128;
+
+/// Hash of a leaf script, given as it is serialized in the witness.
+pub fn tap_leaf_hash(leaf_version: u8, script: &[u8]) -> [u8; 32] {
+    let mut data = vec![leaf_version];
+    data.extend(varint::encode(script.len() as u64));
+    data.extend_from_slice(script);
+
+    hashes::tagged_hash("TapLeaf", &data)
+}
+
+/// Hash of two sibling nodes, sorted so that a merkle path needs no left or right flags.
+pub fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+
+    hashes::tagged_hash("TapBranch", &[&left[..], &right[..]].concat())
+}
+
+/// `t`, the scalar the internal key is tweaked by to commit to the script tree, if there is one.
+pub fn tap_tweak_hash(internal_key: &XOnlyPublicKey, merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut data = internal_key.serialize().to_vec();
+    if let Some(merkle_root) = merkle_root {
+        data.extend_from_slice(merkle_root);
+    }
+
+    hashes::tagged_hash("TapTweak", &data)
+}
+
+/// The output key `Q = P + t * G` and whether its y is odd, which control blocks record.
+pub fn tweak_public_key(
+    internal_key: &XOnlyPublicKey,
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<(XOnlyPublicKey, bool), crate::Error> {
+    let tweak = tweak_scalar(internal_key, merkle_root)?;
+    let output_key = internal_key.point() + G.scalar_mul_u256(tweak);
+
+    Ok((
+        XOnlyPublicKey::from_point(&output_key)?,
+        !output_key.has_even_y(),
+    ))
+}
+
+/// The private key of the output key, for key path spends.
+pub fn tweak_private_key(
+    private_key: &PrivateKey,
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<PrivateKey, crate::Error> {
+    // The internal key is the even-y point, so an odd-y key's secret is negated first
+    let secret = if private_key.point().has_even_y() {
+        private_key.secret()
+    } else {
+        N - private_key.secret()
+    };
+
+    let tweak = tweak_scalar(&private_key.x_only_public_key(), merkle_root)?;
+    let secret = secp256k1::scalar(secret).add(secp256k1::scalar(tweak))?;
+
+    PrivateKey::new(secret.num())
+}
+
+fn tweak_scalar(
+    internal_key: &XOnlyPublicKey,
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<U256, crate::Error> {
+    let tweak = U256::from_be_slice(&tap_tweak_hash(internal_key, merkle_root));
+    if tweak >= N {
+        return Err(crate::Error::ValueError(
+            "Tweak is not below the curve order".to_string(),
+        ));
+    }
+
+    Ok(tweak)
+}
+
+/// Splits the annex off a taproot witness, if it has one.
+pub fn split_annex(witness: &[Vec<u8>]) -> (&[Vec<u8>], Option<&[u8]>) {
+    match witness.split_last() {
+        Some((last, rest)) if !rest.is_empty() && last.first() == Some(&ANNEX_TAG) => {
+            (rest, Some(last))
+        }
+        _ => (witness, None),
+    }
+}
+
+/// A script tree. Each leaf can be spent by revealing its script and a control block.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TapTree {
+    Leaf { version: u8, script: Script },
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    /// A tapscript leaf.
+    pub fn leaf(script: Script) -> Self {
+        TapTree::Leaf {
+            version: TAPSCRIPT_LEAF_VERSION,
+            script,
+        }
+    }
+
+    pub fn branch(left: TapTree, right: TapTree) -> Self {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    /// The merkle root the output key commits to. Leaves are hashed as their scripts' bytes,
+    /// so a leaf with non-minimal pushes must be spent with exactly those bytes in the witness.
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf { version, script } => tap_leaf_hash(*version, &script.raw_serialize()),
+            TapTree::Branch(left, right) => tap_branch_hash(&left.hash(), &right.hash()),
+        }
+    }
+
+    /// The control block spending the leaf with `version` and `script`.
+    pub fn control_block(
+        &self,
+        internal_key: &XOnlyPublicKey,
+        version: u8,
+        script: &Script,
+    ) -> Result<ControlBlock, crate::Error> {
+        let merkle_path = self.merkle_path(version, script).ok_or_else(|| {
+            crate::Error::ValueError(format!("No leaf with version {version:#04x} and {script}"))
+        })?;
+        let (_, output_key_parity) = tweak_public_key(internal_key, Some(&self.hash()))?;
+
+        Ok(ControlBlock {
+            leaf_version: version,
+            output_key_parity,
+            internal_key: *internal_key,
+            merkle_path,
+        })
+    }
+
+    /// Hashes of the siblings from the leaf up to the root.
+    fn merkle_path(&self, version: u8, script: &Script) -> Option<Vec<[u8; 32]>> {
+        match self {
+            TapTree::Leaf {
+                version: leaf_version,
+                script: leaf_script,
+            } => (*leaf_version == version && leaf_script == script).then(Vec::new),
+            TapTree::Branch(left, right) => {
+                let (mut path, sibling) = match left.merkle_path(version, script) {
+                    Some(path) => (path, right),
+                    None => (right.merkle_path(version, script)?, left),
+                };
+                path.push(sibling.hash());
+                Some(path)
+            }
+        }
+    }
+}
+
+/// Proves that a leaf is committed to by an output key: the leaf version, the output key
+/// parity, the internal key and the merkle path from the leaf to the root.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ControlBlock {
+    pub leaf_version: u8,
+    /// Whether the output key has an odd y.
+    pub output_key_parity: bool,
+    pub internal_key: XOnlyPublicKey,
+    pub merkle_path: Vec<[u8; 32]>,
+}
+
+impl ControlBlock {
+    pub fn parse(bytes: &[u8]) -> Result<Self, crate::Error> {
+        if bytes.len() < 33
+            || !(bytes.len() - 33).is_multiple_of(32)
+            || (bytes.len() - 33) / 32 > MAX_MERKLE_PATH
+        {
+            return Err(crate::Error::ValueError(format!(
+                "Invalid control block length {}",
+                bytes.len()
+            )));
+        }
+
+        Ok(ControlBlock {
+            leaf_version: bytes[0] & LEAF_VERSION_MASK,
+            output_key_parity: bytes[0] & 1 == 1,
+            internal_key: XOnlyPublicKey::parse(&bytes[1..33])?,
+            merkle_path: bytes[33..]
+                .chunks_exact(32)
+                .map(|hash| hash.try_into().expect("32 bytes"))
+                .collect(),
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = vec![self.leaf_version | u8::from(self.output_key_parity)];
+        result.extend_from_slice(&self.internal_key.serialize());
+        result.extend(self.merkle_path.iter().flatten());
+
+        result
+    }
+
+    /// Whether `output_key` commits to `script`, given as it is serialized in the witness.
+    pub fn verify(&self, output_key: &XOnlyPublicKey, script: &[u8]) -> bool {
+        let merkle_root = self
+            .merkle_path
+            .iter()
+            .fold(tap_leaf_hash(self.leaf_version, script), |node, sibling| {
+                tap_branch_hash(&node, sibling)
+            });
+
+        match tweak_public_key(&self.internal_key, Some(&merkle_root)) {
+            Ok((key, parity)) => key == *output_key && parity == self.output_key_parity,
+            Err(_) => false,
+        }
+    }
+}
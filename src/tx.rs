@@ -0,0 +1,691 @@
+use std::{collections::HashMap, io::Read};
+
+use crypto_bigint::U256;
+
+use crate::{
+    hashes::{self, hash256},
+    hex,
+    script::{self, Script, SigVersion},
+    taproot, varint,
+};
+
+/// Taproot only: signs all inputs and outputs, like `SIGHASH_ALL`, without a sighash byte.
+pub const SIGHASH_DEFAULT: u32 = 0x00;
+/// Signs all inputs and outputs.
+pub const SIGHASH_ALL: u32 = 0x01;
+/// Signs the inputs but none of the outputs.
+pub const SIGHASH_NONE: u32 = 0x02;
+/// Signs the inputs and only the output with the same index as the input being signed.
+pub const SIGHASH_SINGLE: u32 = 0x03;
+/// Combined with one of the above, signs only the input being signed.
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// The 21 million bitcoin that will ever exist, in satoshis. No amount, nor sum of amounts in a
+/// transaction, can be above it.
+pub const MAX_MONEY: u64 = 21_000_000 * 100_000_000;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tx {
+    pub version: u32,
+    pub tx_ins: Vec<TxIn>,
+    pub tx_outs: Vec<TxOut>,
+    pub locktime: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TxIn {
+    /// Hash of the previous transaction, in the order it is displayed (the reverse of how it
+    /// is serialized).
+    pub prev_tx: [u8; 32],
+    pub prev_index: u32,
+    pub script_sig: Script,
+    pub sequence: u32,
+    /// Witness stack, empty for inputs spending legacy outputs.
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TxOut {
+    /// Amount in satoshis.
+    pub amount: u64,
+    pub script_pubkey: Script,
+}
+
+/// Looks up the outputs that transaction inputs spend.
+pub trait UtxoProvider {
+    fn fetch(&self, prev_tx: &[u8; 32], prev_index: u32) -> Result<TxOut, crate::Error>;
+}
+
+/// A `UtxoProvider` holding outputs in memory, keyed by transaction hash (in display order) and
+/// output index.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryUtxoProvider {
+    utxos: HashMap<([u8; 32], u32), TxOut>,
+}
+
+impl MemoryUtxoProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, prev_tx: [u8; 32], prev_index: u32, tx_out: TxOut) {
+        self.utxos.insert((prev_tx, prev_index), tx_out);
+    }
+
+    /// Adds every output of `tx`.
+    pub fn insert_tx(&mut self, tx: &Tx) {
+        let hash = tx.hash();
+        for (index, tx_out) in tx.tx_outs.iter().enumerate() {
+            self.insert(hash, index as u32, tx_out.clone());
+        }
+    }
+}
+
+impl UtxoProvider for MemoryUtxoProvider {
+    fn fetch(&self, prev_tx: &[u8; 32], prev_index: u32) -> Result<TxOut, crate::Error> {
+        self.utxos
+            .get(&(*prev_tx, prev_index))
+            .cloned()
+            .ok_or_else(|| {
+                crate::Error::MissingUtxo(format!("{}:{prev_index}", hex::encode(prev_tx)))
+            })
+    }
+}
+
+/// Adds two amounts, failing if the sum overflows or is above `MAX_MONEY`.
+pub(crate) fn add_amounts(a: u64, b: u64) -> Result<u64, crate::Error> {
+    a.checked_add(b)
+        .filter(|sum| *sum <= MAX_MONEY)
+        .ok_or_else(|| {
+            crate::Error::ValueError(format!("{a} + {b} satoshis is above the maximum amount"))
+        })
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, crate::Error> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_var_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, crate::Error> {
+    let len = varint::read(reader)?;
+
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(crate::Error::ValueError(format!(
+            "Expected {len} bytes, found {}",
+            bytes.len()
+        )));
+    }
+
+    Ok(bytes)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, crate::Error> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u64::from_le_bytes(buf))
+}
+
+impl Tx {
+    /// Parses both legacy and segwit (BIP-144) serializations.
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, crate::Error> {
+        let version = read_u32(reader)?;
+
+        // A segwit marker reads as a count of zero inputs
+        let mut num_inputs = varint::read(reader)?;
+        let segwit = num_inputs == 0;
+        if segwit {
+            let mut flag = [0u8];
+            reader.read_exact(&mut flag)?;
+            if flag[0] != 0x01 {
+                return Err(crate::Error::ValueError(format!(
+                    "Unknown segwit flag {}",
+                    flag[0]
+                )));
+            }
+            num_inputs = varint::read(reader)?;
+        }
+
+        let mut tx_ins = (0..num_inputs)
+            .map(|_| TxIn::parse(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let num_outputs = varint::read(reader)?;
+        let tx_outs = (0..num_outputs)
+            .map(|_| TxOut::parse(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if segwit {
+            for tx_in in &mut tx_ins {
+                let num_items = varint::read(reader)?;
+                tx_in.witness = (0..num_items)
+                    .map(|_| read_var_bytes(reader))
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+
+            if tx_ins.iter().all(|tx_in| tx_in.witness.is_empty()) {
+                return Err(crate::Error::ValueError(
+                    "Segwit serialization without witnesses".to_string(),
+                ));
+            }
+        }
+
+        let locktime = read_u32(reader)?;
+
+        Ok(Tx {
+            version,
+            tx_ins,
+            tx_outs,
+            locktime,
+        })
+    }
+
+    /// Whether any input has a witness, which calls for the segwit serialization.
+    pub fn is_segwit(&self) -> bool {
+        self.tx_ins.iter().any(|tx_in| !tx_in.witness.is_empty())
+    }
+
+    /// The segwit serialization if the transaction has witnesses, the legacy one otherwise.
+    pub fn serialize(&self) -> Vec<u8> {
+        if !self.is_segwit() {
+            return self.serialize_legacy();
+        }
+
+        let mut result = self.version.to_le_bytes().to_vec();
+        result.extend_from_slice(&[0x00, 0x01]);
+        result.extend(self.serialize_ins_outs());
+
+        for tx_in in &self.tx_ins {
+            result.extend(varint::encode(tx_in.witness.len() as u64));
+            for item in &tx_in.witness {
+                result.extend(varint::encode(item.len() as u64));
+                result.extend_from_slice(item);
+            }
+        }
+
+        result.extend_from_slice(&self.locktime.to_le_bytes());
+
+        result
+    }
+
+    /// The serialization without witnesses, which the transaction hash commits to.
+    pub fn serialize_legacy(&self) -> Vec<u8> {
+        let mut result = self.version.to_le_bytes().to_vec();
+        result.extend(self.serialize_ins_outs());
+        result.extend_from_slice(&self.locktime.to_le_bytes());
+
+        result
+    }
+
+    fn serialize_ins_outs(&self) -> Vec<u8> {
+        let mut result = varint::encode(self.tx_ins.len() as u64);
+        for tx_in in &self.tx_ins {
+            result.extend(tx_in.serialize());
+        }
+
+        result.extend(varint::encode(self.tx_outs.len() as u64));
+        for tx_out in &self.tx_outs {
+            result.extend(tx_out.serialize());
+        }
+
+        result
+    }
+
+    /// hash256 of the serialization without witnesses, in display order.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hash = hash256(&self.serialize_legacy());
+        hash.reverse();
+
+        hash
+    }
+
+    /// Hex of the transaction hash, as shown by block explorers.
+    pub fn id(&self) -> String {
+        hex::encode(&self.hash())
+    }
+
+    /// hash256 of the serialization including witnesses, in display order. Equal to `hash` for
+    /// transactions without witnesses.
+    pub fn witness_hash(&self) -> [u8; 32] {
+        let mut hash = hash256(&self.serialize());
+        hash.reverse();
+
+        hash
+    }
+
+    /// Hex of the witness hash.
+    pub fn wtxid(&self) -> String {
+        hex::encode(&self.witness_hash())
+    }
+
+    /// Weight units as defined by BIP-141: legacy bytes count four times, witness bytes once.
+    pub fn weight(&self) -> usize {
+        3 * self.serialize_legacy().len() + self.serialize().len()
+    }
+
+    /// Virtual size, the weight divided by four and rounded up.
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    /// Sum of the inputs minus the sum of the outputs. Fails if the outputs are larger, or if
+    /// either sum is above `MAX_MONEY`.
+    pub fn fee(&self, utxos: &impl UtxoProvider) -> Result<u64, crate::Error> {
+        let input_sum = self
+            .tx_ins
+            .iter()
+            .try_fold(0, |sum, tx_in| add_amounts(sum, tx_in.value(utxos)?))?;
+        let output_sum = self
+            .tx_outs
+            .iter()
+            .try_fold(0, |sum, tx_out| add_amounts(sum, tx_out.amount))?;
+
+        input_sum.checked_sub(output_sum).ok_or_else(|| {
+            crate::Error::ValueError(format!(
+                "Outputs ({output_sum}) exceed inputs ({input_sum})"
+            ))
+        })
+    }
+
+    /// Runs the scripts of input `input_index` against the output it spends.
+    pub fn verify_input(
+        &self,
+        input_index: usize,
+        utxos: &impl UtxoProvider,
+    ) -> Result<(), crate::Error> {
+        let tx_in = self
+            .tx_ins
+            .get(input_index)
+            .ok_or_else(|| crate::Error::ValueError(format!("No input at index {input_index}")))?;
+        let prev_out = utxos.fetch(&tx_in.prev_tx, tx_in.prev_index)?;
+
+        // Taproot signatures commit to every output being spent
+        let prevouts = match prev_out.script_pubkey.witness_program() {
+            Some((1, program)) if program.len() == 32 => self
+                .tx_ins
+                .iter()
+                .map(|tx_in| utxos.fetch(&tx_in.prev_tx, tx_in.prev_index))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => Vec::new(),
+        };
+        let (_, annex) = taproot::split_annex(&tx_in.witness);
+
+        // Each signature commits to the sighash type in its last byte, except 64 byte Schnorr
+        // signatures which use SIGHASH_DEFAULT
+        let check_sig = |sig_version, script_code: &Script, sig: &[u8], public_key: &[u8]| {
+            let leaf = match sig_version {
+                SigVersion::Base | SigVersion::WitnessV0 => {
+                    let Some(sighash_type) = sig.last().map(|byte| u32::from(*byte)) else {
+                        return false;
+                    };
+
+                    let z = if sig_version == SigVersion::Base {
+                        self.sig_hash(input_index, script_code, sighash_type)
+                    } else {
+                        let Ok(z) = self.sig_hash_bip143(
+                            input_index,
+                            script_code,
+                            prev_out.amount,
+                            sighash_type,
+                        ) else {
+                            return false;
+                        };
+                        z
+                    };
+
+                    return script::check_ecdsa(z, sig, public_key);
+                }
+                SigVersion::Taproot => None,
+                SigVersion::Tapscript {
+                    leaf_hash,
+                    codesep_pos,
+                } => Some((leaf_hash, codesep_pos)),
+            };
+
+            let (sig, sighash_type) = match sig {
+                [sig @ .., sighash_type] if sig.len() == 64 && *sighash_type != 0 => {
+                    (sig, u32::from(*sighash_type))
+                }
+                sig if sig.len() == 64 => (sig, SIGHASH_DEFAULT),
+                _ => return false,
+            };
+
+            match self.sig_hash_taproot(input_index, &prevouts, sighash_type, annex, leaf) {
+                Ok(msg) => script::check_schnorr(&msg, sig, public_key),
+                Err(_) => false,
+            }
+        };
+
+        Script::verify_spend(
+            &tx_in.script_sig,
+            &prev_out.script_pubkey,
+            &tx_in.witness,
+            &check_sig,
+        )
+    }
+
+    /// Checks that the inputs cover the outputs and that every input is validly signed.
+    pub fn verify(&self, utxos: &impl UtxoProvider) -> Result<(), crate::Error> {
+        self.fee(utxos)?;
+
+        for input_index in 0..self.tx_ins.len() {
+            self.verify_input(input_index, utxos)?;
+        }
+
+        Ok(())
+    }
+
+    /// Legacy signature hash of input `input_index`, with `script_pubkey` being the script of
+    /// the output it spends, or the redeem script for P2SH outputs. The result is the z that
+    /// signatures commit to. Once an `OP_CODESEPARATOR` has run, only the part of the script
+    /// after it is signed; any separators left in `script_pubkey` are not hashed.
+    ///
+    /// Out of range inputs, and `SIGHASH_SINGLE` without a matching output, hash to the value
+    /// one (as little-endian bytes), as the original implementation does.
+    pub fn sig_hash(&self, input_index: usize, script_pubkey: &Script, sighash_type: u32) -> U256 {
+        let base_type = sighash_type & 0x1f;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+
+        if input_index >= self.tx_ins.len()
+            || (base_type == SIGHASH_SINGLE && input_index >= self.tx_outs.len())
+        {
+            return U256::ONE.shl_vartime(248);
+        }
+
+        let script_code = script_pubkey.remove_codeseparators();
+
+        let tx_ins: Vec<TxIn> = self
+            .tx_ins
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !anyone_can_pay || *i == input_index)
+            .map(|(i, tx_in)| {
+                let mut tx_in = tx_in.clone();
+                if i == input_index {
+                    tx_in.script_sig = script_code.clone();
+                } else {
+                    tx_in.script_sig = Script::default();
+                    if base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+                        tx_in.sequence = 0;
+                    }
+                }
+                tx_in
+            })
+            .collect();
+
+        let tx_outs = match base_type {
+            SIGHASH_NONE => Vec::new(),
+            SIGHASH_SINGLE => {
+                let mut tx_outs = vec![TxOut::new(u64::MAX, Script::default()); input_index];
+                tx_outs.push(self.tx_outs[input_index].clone());
+                tx_outs
+            }
+            _ => self.tx_outs.clone(),
+        };
+
+        let tx = Tx {
+            version: self.version,
+            tx_ins,
+            tx_outs,
+            locktime: self.locktime,
+        };
+
+        let mut preimage = tx.serialize_legacy();
+        preimage.extend_from_slice(&sighash_type.to_le_bytes());
+
+        U256::from_be_slice(&hash256(&preimage))
+    }
+
+    /// BIP-143 signature hash of input `input_index`, which spends `amount` satoshis from a
+    /// segwit v0 output. `script_code` is the witness script for P2WSH, and the P2PKH script of
+    /// the key hash for P2WPKH. Fails if there is no input `input_index`.
+    pub fn sig_hash_bip143(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        amount: u64,
+        sighash_type: u32,
+    ) -> Result<U256, crate::Error> {
+        let base_type = sighash_type & 0x1f;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let tx_in = self
+            .tx_ins
+            .get(input_index)
+            .ok_or_else(|| crate::Error::ValueError(format!("No input at index {input_index}")))?;
+
+        let hash_prevouts = if anyone_can_pay {
+            [0; 32]
+        } else {
+            let prevouts: Vec<u8> = self.tx_ins.iter().flat_map(TxIn::outpoint).collect();
+            hash256(&prevouts)
+        };
+
+        let hash_sequence =
+            if anyone_can_pay || base_type == SIGHASH_SINGLE || base_type == SIGHASH_NONE {
+                [0; 32]
+            } else {
+                let sequences: Vec<u8> = self
+                    .tx_ins
+                    .iter()
+                    .flat_map(|tx_in| tx_in.sequence.to_le_bytes())
+                    .collect();
+                hash256(&sequences)
+            };
+
+        let hash_outputs = match base_type {
+            SIGHASH_NONE => [0; 32],
+            SIGHASH_SINGLE => match self.tx_outs.get(input_index) {
+                Some(tx_out) => hash256(&tx_out.serialize()),
+                None => [0; 32],
+            },
+            _ => {
+                let outputs: Vec<u8> = self.tx_outs.iter().flat_map(TxOut::serialize).collect();
+                hash256(&outputs)
+            }
+        };
+
+        let mut preimage = self.version.to_le_bytes().to_vec();
+        preimage.extend_from_slice(&hash_prevouts);
+        preimage.extend_from_slice(&hash_sequence);
+        preimage.extend(tx_in.outpoint());
+        preimage.extend(script_code.serialize());
+        preimage.extend_from_slice(&amount.to_le_bytes());
+        preimage.extend_from_slice(&tx_in.sequence.to_le_bytes());
+        preimage.extend_from_slice(&hash_outputs);
+        preimage.extend_from_slice(&self.locktime.to_le_bytes());
+        preimage.extend_from_slice(&sighash_type.to_le_bytes());
+
+        Ok(U256::from_be_slice(&hash256(&preimage)))
+    }
+
+    /// BIP-341 signature hash of input `input_index`, the message Schnorr signatures sign.
+    /// `prevouts` are the outputs spent by every input, in order. Script path spends pass the
+    /// hash of the leaf being run and the position of its last executed `OP_CODESEPARATOR`
+    /// (`u32::MAX` if none) as `leaf`.
+    ///
+    /// Unlike the legacy algorithm, undefined sighash types and `SIGHASH_SINGLE` without a
+    /// matching output are errors.
+    pub fn sig_hash_taproot(
+        &self,
+        input_index: usize,
+        prevouts: &[TxOut],
+        sighash_type: u32,
+        annex: Option<&[u8]>,
+        leaf: Option<([u8; 32], u32)>,
+    ) -> Result<[u8; 32], crate::Error> {
+        if !matches!(sighash_type, 0x00..=0x03 | 0x81..=0x83) {
+            return Err(crate::Error::ValueError(format!(
+                "Invalid taproot sighash type {sighash_type:#04x}"
+            )));
+        }
+        if prevouts.len() != self.tx_ins.len() {
+            return Err(crate::Error::ValueError(format!(
+                "Expected {} prevouts, got {}",
+                self.tx_ins.len(),
+                prevouts.len()
+            )));
+        }
+        let tx_in = self
+            .tx_ins
+            .get(input_index)
+            .ok_or_else(|| crate::Error::ValueError(format!("No input at index {input_index}")))?;
+
+        let base_type = sighash_type & 0x03;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+
+        // Epoch 0, then the sighash type
+        let mut msg = vec![0x00, sighash_type as u8];
+        msg.extend_from_slice(&self.version.to_le_bytes());
+        msg.extend_from_slice(&self.locktime.to_le_bytes());
+
+        if !anyone_can_pay {
+            let outpoints: Vec<u8> = self.tx_ins.iter().flat_map(TxIn::outpoint).collect();
+            let amounts: Vec<u8> = prevouts
+                .iter()
+                .flat_map(|prevout| prevout.amount.to_le_bytes())
+                .collect();
+            let script_pubkeys: Vec<u8> = prevouts
+                .iter()
+                .flat_map(|prevout| prevout.script_pubkey.serialize())
+                .collect();
+            let sequences: Vec<u8> = self
+                .tx_ins
+                .iter()
+                .flat_map(|tx_in| tx_in.sequence.to_le_bytes())
+                .collect();
+
+            msg.extend_from_slice(&hashes::sha256(&outpoints));
+            msg.extend_from_slice(&hashes::sha256(&amounts));
+            msg.extend_from_slice(&hashes::sha256(&script_pubkeys));
+            msg.extend_from_slice(&hashes::sha256(&sequences));
+        }
+
+        if base_type != SIGHASH_NONE && base_type != SIGHASH_SINGLE {
+            let outputs: Vec<u8> = self.tx_outs.iter().flat_map(TxOut::serialize).collect();
+            msg.extend_from_slice(&hashes::sha256(&outputs));
+        }
+
+        let spend_type = 2 * u8::from(leaf.is_some()) + u8::from(annex.is_some());
+        msg.push(spend_type);
+
+        if anyone_can_pay {
+            let prevout = &prevouts[input_index];
+            msg.extend(tx_in.outpoint());
+            msg.extend_from_slice(&prevout.amount.to_le_bytes());
+            msg.extend(prevout.script_pubkey.serialize());
+            msg.extend_from_slice(&tx_in.sequence.to_le_bytes());
+        } else {
+            msg.extend_from_slice(&(input_index as u32).to_le_bytes());
+        }
+
+        if let Some(annex) = annex {
+            let mut data = varint::encode(annex.len() as u64);
+            data.extend_from_slice(annex);
+            msg.extend_from_slice(&hashes::sha256(&data));
+        }
+
+        if base_type == SIGHASH_SINGLE {
+            let tx_out = self.tx_outs.get(input_index).ok_or_else(|| {
+                crate::Error::ValueError(format!("No output at index {input_index}"))
+            })?;
+            msg.extend_from_slice(&hashes::sha256(&tx_out.serialize()));
+        }
+
+        if let Some((leaf_hash, codesep_pos)) = leaf {
+            msg.extend_from_slice(&leaf_hash);
+            // Key version 0, for the 32 byte keys of BIP-342
+            msg.push(0x00);
+            msg.extend_from_slice(&codesep_pos.to_le_bytes());
+        }
+
+        Ok(hashes::tagged_hash("TapSighash", &msg))
+    }
+}
+
+impl TxIn {
+    pub fn new(prev_tx: [u8; 32], prev_index: u32) -> Self {
+        TxIn {
+            prev_tx,
+            prev_index,
+            script_sig: Script::default(),
+            sequence: 0xffffffff,
+            witness: Vec::new(),
+        }
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, crate::Error> {
+        let mut prev_tx = [0u8; 32];
+        reader.read_exact(&mut prev_tx)?;
+        prev_tx.reverse();
+
+        let prev_index = read_u32(reader)?;
+        let script_sig = Script::parse(reader)?;
+        let sequence = read_u32(reader)?;
+
+        Ok(TxIn {
+            prev_tx,
+            prev_index,
+            script_sig,
+            sequence,
+            witness: Vec::new(),
+        })
+    }
+
+    /// Amount of the output this input spends.
+    pub fn value(&self, utxos: &impl UtxoProvider) -> Result<u64, crate::Error> {
+        Ok(utxos.fetch(&self.prev_tx, self.prev_index)?.amount)
+    }
+
+    /// Script of the output this input spends.
+    pub fn script_pubkey(&self, utxos: &impl UtxoProvider) -> Result<Script, crate::Error> {
+        Ok(utxos.fetch(&self.prev_tx, self.prev_index)?.script_pubkey)
+    }
+
+    /// The previous transaction hash and output index, as serialized.
+    fn outpoint(&self) -> Vec<u8> {
+        let mut result = self.prev_tx.to_vec();
+        result.reverse();
+        result.extend_from_slice(&self.prev_index.to_le_bytes());
+
+        result
+    }
+
+    /// Serialization without the witness, which goes at the end of the transaction.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = self.outpoint();
+        result.extend(self.script_sig.serialize());
+        result.extend_from_slice(&self.sequence.to_le_bytes());
+
+        result
+    }
+}
+
+impl TxOut {
+    pub fn new(amount: u64, script_pubkey: Script) -> Self {
+        TxOut {
+            amount,
+            script_pubkey,
+        }
+    }
+
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self, crate::Error> {
+        let amount = read_u64(reader)?;
+        let script_pubkey = Script::parse(reader)?;
+
+        Ok(TxOut {
+            amount,
+            script_pubkey,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = self.amount.to_le_bytes().to_vec();
+        result.extend(self.script_pubkey.serialize());
+
+        result
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    hex,
+    private_key::PrivateKey,
+    script::{Command, Opcode, Script},
+    tx::{self, Tx, TxIn, TxOut},
+};
+
+/// Size of a DER signature with a low R and a low S plus the sighash byte, used to estimate
+/// the size of a transaction before signing it.
+const SIGNATURE_SIZE: usize = 71;
+
+/// The amount below which an output locked to `script_pubkey` is dust under Bitcoin Core's
+/// default relay policy: three times the size of the output plus the input spending it. That is
+/// 546 satoshis for P2PKH, 540 for P2SH, 294 for P2WPKH and 330 for P2WSH and P2TR outputs.
+/// Change below it is not worth an output and goes to the fee instead.
+pub fn dust_threshold(script_pubkey: &Script) -> u64 {
+    if script_pubkey.raw_serialize().first() == Some(&Opcode::OP_RETURN.to_byte()) {
+        return 0;
+    }
+
+    // Outpoint, script_sig length and sequence, plus a signature and a compressed public key
+    // in the script_sig or, discounted, in the witness
+    let spend_size = if script_pubkey.witness_program().is_some() {
+        32 + 4 + 1 + 107 / 4 + 4
+    } else {
+        32 + 4 + 1 + 107 + 4
+    };
+    let output_size = TxOut::new(0, script_pubkey.clone()).serialize().len();
+
+    3 * (output_size + spend_size) as u64
+}
+
+#[derive(Debug, Clone)]
+struct Utxo {
+    prev_tx: [u8; 32],
+    prev_index: u32,
+    tx_out: TxOut,
+    private_key: PrivateKey,
+}
+
+/// Builds and signs transactions spending P2PKH, P2WPKH and P2SH-P2WPKH outputs.
+///
+/// The transaction has version 2, a locktime of 0 and final sequences, outputs are kept in the
+/// order they were added with the change last, and inputs are signed with `SIGHASH_ALL` and
+/// low-R signatures.
+#[derive(Debug, Clone)]
+pub struct TxBuilder {
+    /// Fee rate in satoshis per virtual byte.
+    fee_rate: u64,
+    change_address: String,
+    utxos: Vec<Utxo>,
+    outputs: Vec<(String, u64)>,
+}
+
+impl TxBuilder {
+    pub fn new(fee_rate: u64, change_address: &str) -> Self {
+        TxBuilder {
+            fee_rate,
+            change_address: change_address.to_string(),
+            utxos: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Spends output `prev_index` of `prev_tx`, which is `tx_out` and is locked to
+    /// `private_key`.
+    pub fn add_utxo(
+        mut self,
+        prev_tx: [u8; 32],
+        prev_index: u32,
+        tx_out: TxOut,
+        private_key: PrivateKey,
+    ) -> Self {
+        self.utxos.push(Utxo {
+            prev_tx,
+            prev_index,
+            tx_out,
+            private_key,
+        });
+        self
+    }
+
+    /// Pays `amount` satoshis to `address`.
+    pub fn add_output(mut self, address: &str, amount: u64) -> Self {
+        self.outputs.push((address.to_string(), amount));
+        self
+    }
+
+    /// The signed transaction. Fails if there are no UTXOs, if they do not cover the outputs
+    /// and the fee, or if the change is dust and there are no other outputs.
+    pub fn build(self) -> Result<Tx, crate::Error> {
+        if self.utxos.is_empty() {
+            return Err(crate::Error::ValueError("No UTXOs to spend".to_string()));
+        }
+
+        let spends = self
+            .utxos
+            .iter()
+            .map(Spend::of)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tx_outs = self
+            .outputs
+            .iter()
+            .map(|(address, amount)| Ok(TxOut::new(*amount, Script::from_address(address)?)))
+            .collect::<Result<Vec<_>, crate::Error>>()?;
+
+        let mut tx = Tx {
+            version: 2,
+            tx_ins: self
+                .utxos
+                .iter()
+                .map(|utxo| TxIn::new(utxo.prev_tx, utxo.prev_index))
+                .collect(),
+            tx_outs,
+            locktime: 0,
+        };
+
+        let input_sum = self
+            .utxos
+            .iter()
+            .try_fold(0, |sum, utxo| tx::add_amounts(sum, utxo.tx_out.amount))?;
+        let output_sum = tx
+            .tx_outs
+            .iter()
+            .try_fold(0, |sum, tx_out| tx::add_amounts(sum, tx_out.amount))?;
+
+        let change = TxOut::new(0, Script::from_address(&self.change_address)?);
+        let vsize = estimate_vsize(&tx, &self.utxos, &spends);
+        let fee_without_change = self.fee(vsize)?;
+        let fee_with_change = self.fee(vsize + change.serialize().len())?;
+
+        let needed = tx::add_amounts(output_sum, fee_without_change)?;
+        if input_sum < needed {
+            return Err(crate::Error::ValueError(format!(
+                "Insufficient funds: {input_sum} available, {needed} needed"
+            )));
+        }
+
+        let change_amount = output_sum
+            .checked_add(fee_with_change)
+            .and_then(|needed| input_sum.checked_sub(needed))
+            .unwrap_or(0);
+        if change_amount >= dust_threshold(&change.script_pubkey) {
+            tx.tx_outs
+                .push(TxOut::new(change_amount, change.script_pubkey));
+        }
+        if tx.tx_outs.is_empty() {
+            return Err(crate::Error::ValueError(format!(
+                "No outputs: the change of {change_amount} satoshis is dust"
+            )));
+        }
+
+        for (input_index, (utxo, spend)) in self.utxos.iter().zip(&spends).enumerate() {
+            let z = match spend {
+                Spend::P2pkh { .. } => {
+                    tx.sig_hash(input_index, &utxo.tx_out.script_pubkey, tx::SIGHASH_ALL)
+                }
+                Spend::P2wpkh | Spend::P2shP2wpkh => {
+                    let script_code = Script::p2pkh(&utxo.private_key.point().hash160(true));
+                    tx.sig_hash_bip143(
+                        input_index,
+                        &script_code,
+                        utxo.tx_out.amount,
+                        tx::SIGHASH_ALL,
+                    )?
+                }
+            };
+
+            let mut sig = utxo.private_key.sign_low_r(z)?.der();
+            sig.push(tx::SIGHASH_ALL as u8);
+
+            spend.unlock(&mut tx.tx_ins[input_index], &utxo.private_key, sig);
+        }
+
+        Ok(tx)
+    }
+
+    /// Fee for `vsize` virtual bytes at the builder's fee rate.
+    fn fee(&self, vsize: usize) -> Result<u64, crate::Error> {
+        self.fee_rate
+            .checked_mul(vsize as u64)
+            .filter(|fee| *fee <= tx::MAX_MONEY)
+            .ok_or_else(|| {
+                crate::Error::ValueError(format!(
+                    "Fee rate of {} sat/vB is too high for {vsize} vbytes",
+                    self.fee_rate
+                ))
+            })
+    }
+
+    /// The signed transaction, serialized as hex.
+    pub fn build_hex(self) -> Result<String, crate::Error> {
+        Ok(hex::encode(&self.build()?.serialize()))
+    }
+}
+
+/// The kinds of outputs the builder knows how to sign for.
+#[derive(Debug, Clone, Copy)]
+enum Spend {
+    P2pkh {
+        compressed: bool,
+    },
+    P2wpkh,
+    /// P2WPKH nested in P2SH.
+    P2shP2wpkh,
+}
+
+impl Spend {
+    fn of(utxo: &Utxo) -> Result<Spend, crate::Error> {
+        let script_pubkey = &utxo.tx_out.script_pubkey;
+        let point = utxo.private_key.point();
+
+        let spend = if script_pubkey.p2pkh_hash() == Some(point.hash160(true)) {
+            Spend::P2pkh { compressed: true }
+        } else if script_pubkey.p2pkh_hash() == Some(point.hash160(false)) {
+            Spend::P2pkh { compressed: false }
+        } else if *script_pubkey == Script::p2wpkh(&point.hash160(true)) {
+            Spend::P2wpkh
+        } else if *script_pubkey == Script::p2wpkh(&point.hash160(true)).to_p2sh() {
+            Spend::P2shP2wpkh
+        } else {
+            return Err(crate::Error::ValueError(format!(
+                "Private key does not unlock {script_pubkey}"
+            )));
+        };
+
+        Ok(spend)
+    }
+
+    /// Fills in the script_sig and witness of `tx_in` given its signature.
+    fn unlock(self, tx_in: &mut TxIn, private_key: &PrivateKey, sig: Vec<u8>) {
+        let point = private_key.point();
+
+        match self {
+            Spend::P2pkh { compressed } => {
+                tx_in.script_sig = Script::new(vec![
+                    Command::Data(sig),
+                    Command::Data(point.sec(compressed)),
+                ]);
+            }
+            Spend::P2wpkh => {
+                tx_in.witness = vec![sig, point.sec(true)];
+            }
+            Spend::P2shP2wpkh => {
+                let redeem_script = Script::p2wpkh(&point.hash160(true));
+                tx_in.script_sig = Script::new(vec![Command::Data(redeem_script.raw_serialize())]);
+                tx_in.witness = vec![sig, point.sec(true)];
+            }
+        }
+    }
+}
+
+/// Virtual size of `tx` once its inputs are signed.
+fn estimate_vsize(tx: &Tx, utxos: &[Utxo], spends: &[Spend]) -> usize {
+    let mut tx = tx.clone();
+
+    for ((tx_in, utxo), spend) in tx.tx_ins.iter_mut().zip(utxos).zip(spends) {
+        spend.unlock(tx_in, &utxo.private_key, vec![0; SIGNATURE_SIZE]);
+    }
+
+    tx.vsize()
+}
@@ -0,0 +1,45 @@
+/// Reads a variable length integer: values below 0xfd take one byte, larger values are
+/// prefixed with 0xfd, 0xfe or 0xff followed by 2, 4 or 8 little endian bytes.
+pub fn read<R: std::io::Read>(reader: &mut R) -> Result<u64, crate::Error> {
+    let mut prefix = [0u8; 1];
+    reader.read_exact(&mut prefix)?;
+
+    let value = match prefix[0] {
+        0xfd => {
+            let mut buf = [0u8; 2];
+            reader.read_exact(&mut buf)?;
+            u64::from(u16::from_le_bytes(buf))
+        }
+        0xfe => {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            u64::from(u32::from_le_bytes(buf))
+        }
+        0xff => {
+            let mut buf = [0u8; 8];
+            reader.read_exact(&mut buf)?;
+            u64::from_le_bytes(buf)
+        }
+        value => u64::from(value),
+    };
+
+    Ok(value)
+}
+
+pub fn encode(value: u64) -> Vec<u8> {
+    if value < 0xfd {
+        vec![value as u8]
+    } else if value <= 0xffff {
+        let mut result = vec![0xfd];
+        result.extend_from_slice(&(value as u16).to_le_bytes());
+        result
+    } else if value <= 0xffff_ffff {
+        let mut result = vec![0xfe];
+        result.extend_from_slice(&(value as u32).to_le_bytes());
+        result
+    } else {
+        let mut result = vec![0xff];
+        result.extend_from_slice(&value.to_le_bytes());
+        result
+    }
+}